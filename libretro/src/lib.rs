@@ -0,0 +1,529 @@
+//! libretro core wrapping `Chip8Context`, for RetroArch and other libretro frontends.
+
+use chip_8::{
+    BEEP_FREQUENCY, BEEP_VOLUME, Chip8Context, PALETTE, Quirks, TARGET_IPS,
+    cheats::{Cheat, CheatEffect},
+    framebuffer::{HIRES, LORES},
+    pacing::FRAME_RATE,
+    platform::Platform,
+    rom::{Rom, RomError},
+};
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{CStr, CString, c_char, c_uint, c_void},
+    path::Path,
+    ptr, slice,
+    sync::Mutex,
+};
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_ENVIRONMENT_GET_LOG_INTERFACE: c_uint = 27;
+const RETRO_LOG_ERROR: c_uint = 3;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FRAME_RATE) as usize;
+
+/// Joypad button ids and the keypad keys they press. The d-pad maps to the usual 2/4/6/8 layout.
+const JOYPAD_KEYS: [(c_uint, u8); 16] = [
+    (4, 0x2),  // UP
+    (5, 0x8),  // DOWN
+    (6, 0x4),  // LEFT
+    (7, 0x6),  // RIGHT
+    (8, 0x5),  // A
+    (0, 0x0),  // B
+    (9, 0x1),  // X
+    (1, 0x3),  // Y
+    (10, 0x7), // L
+    (11, 0x9), // R
+    (12, 0xA), // L2
+    (13, 0xB), // R2
+    (2, 0xC),  // SELECT
+    (14, 0xD), // L3
+    (15, 0xE), // R3
+    (3, 0xF),  // START
+];
+
+type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type LogFn = unsafe extern "C" fn(level: c_uint, fmt: *const c_char, ...);
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+#[repr(C)]
+struct RetroVariable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+struct RetroLogCallback {
+    log: Option<LogFn>,
+}
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    context: Chip8Context,
+    phase: f32,
+    // Set when an instruction failed, e.g. with a stack overflow; the machine stays stopped
+    // until it is reset.
+    halted: bool,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+thread_local! {
+    // `Chip8Context` is not `Send`; frontends drive a core from a single thread.
+    static CORE: RefCell<Option<Core>> = const { RefCell::new(None) };
+}
+
+impl Core {
+    fn new(rom: Rom) -> Result<Self, RomError> {
+        let mut core = Core {
+            context: Chip8Context::builder(rom.program)
+                .platform(Platform::for_format(rom.format))
+                .build()?,
+            phase: 0.0,
+            halted: false,
+        };
+        core.apply_options();
+        Ok(core)
+    }
+
+    fn apply_options(&mut self) {
+        let ips = variable(c"chip8_ips")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(TARGET_IPS);
+        self.context.set_ips(ips);
+        self.context.set_quirks(Quirks {
+            shift: variable(c"chip8_quirk_shift").as_deref() == Some("enabled"),
+            jump: variable(c"chip8_quirk_jump").as_deref() == Some("enabled"),
+            store_load: variable(c"chip8_quirk_store_load").as_deref() == Some("enabled"),
+        });
+    }
+
+    /// Runs one frame and hands its video and audio to the frontend. Fails if an instruction
+    /// failed, after which the machine stays halted.
+    fn run_frame(&mut self, callbacks: &Callbacks) -> Result<(), Box<dyn Error>> {
+        let mut keys = [false; 16];
+        if let (Some(input_poll), Some(input_state)) = (callbacks.input_poll, callbacks.input_state)
+        {
+            unsafe { input_poll() };
+            for (id, key) in JOYPAD_KEYS {
+                keys[key as usize] |= unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, id) } != 0;
+            }
+        }
+
+        for (key, &pressed) in keys.iter().enumerate() {
+            self.context.set_key(key as u8, pressed);
+        }
+        let mut result = Ok(());
+        if !self.halted {
+            result = self.context.run_frame();
+            self.halted = result.is_err();
+        }
+
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let frame = self.frame();
+            let display = self.context.display();
+            let (width, height) = (display.width() as c_uint, display.height() as c_uint);
+            unsafe { video_refresh(frame.as_ptr().cast(), width, height, width as usize * 4) };
+        }
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            let samples = self.samples();
+            unsafe { audio_sample_batch(samples.as_ptr(), SAMPLES_PER_FRAME) };
+        }
+        result
+    }
+
+    fn frame(&self) -> Vec<u32> {
+        let xrgb = |color: chip_8::Color| u32::from_be_bytes([0, color.r, color.g, color.b]);
+        let (on, off) = (xrgb(PALETTE.foreground), xrgb(PALETTE.background));
+        self.context
+            .display()
+            .rows()
+            .flatten()
+            .map(|pixel| if pixel { on } else { off })
+            .collect()
+    }
+
+    /// Interleaved stereo samples for one frame of the beeper.
+    fn samples(&mut self) -> Vec<i16> {
+        let beeping = self.context.is_beeping();
+        let phase_inc = BEEP_FREQUENCY / SAMPLE_RATE as f32;
+        let amplitude = (BEEP_VOLUME * i16::MAX as f32) as i16;
+        let mut samples = Vec::with_capacity(SAMPLES_PER_FRAME * 2);
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = match (beeping, self.phase <= 0.5) {
+                (false, _) => 0,
+                (true, true) => amplitude,
+                (true, false) => -amplitude,
+            };
+            samples.extend_from_slice(&[sample, sample]);
+            self.phase = (self.phase + phase_inc) % 1.0;
+        }
+        samples
+    }
+}
+
+fn with_core<R>(f: impl FnOnce(&mut Core) -> R) -> Option<R> {
+    CORE.with_borrow_mut(|core| core.as_mut().map(f))
+}
+
+fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    let environment = CALLBACKS.lock().unwrap().environment;
+    match environment {
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+/// Writes an error to the frontend's log, if it has one.
+fn log_error(error: &dyn Error) {
+    let mut callback = RetroLogCallback { log: None };
+    if !environment(
+        RETRO_ENVIRONMENT_GET_LOG_INTERFACE,
+        (&raw mut callback).cast(),
+    ) {
+        return;
+    }
+    if let Some(log) = callback.log {
+        let message = CString::new(error.to_string()).unwrap_or_default();
+        unsafe { log(RETRO_LOG_ERROR, c"[chip-8] %s\n".as_ptr(), message.as_ptr()) };
+    }
+}
+
+fn variable(key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: ptr::null(),
+    };
+    if !environment(RETRO_ENVIRONMENT_GET_VARIABLE, (&raw mut variable).cast())
+        || variable.value.is_null()
+    {
+        return None;
+    }
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+
+    let mut variables = [
+        RetroVariable {
+            key: c"chip8_ips".as_ptr(),
+            value: c"Instructions per second; 700|500|600|800|900|1000|1200|1500|2000|3000"
+                .as_ptr(),
+        },
+        RetroVariable {
+            key: c"chip8_quirk_shift".as_ptr(),
+            value: c"Shift quirk (8XY6/8XYE copy VY); disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: c"chip8_quirk_jump".as_ptr(),
+            value: c"Jump quirk (BXNN adds VX); disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: c"chip8_quirk_store_load".as_ptr(),
+            value: c"Store/load quirk (FX55/FX65 increment I); disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: ptr::null(),
+            value: ptr::null(),
+        },
+    ];
+    environment(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr().cast(),
+    );
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    CORE.set(None);
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    let info = unsafe { &mut *info };
+    info.library_name = c"CHIP-8".as_ptr();
+    info.library_version = concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast();
+    info.valid_extensions = c"ch8|c8|sc8|xo8|c8x|hex|txt".as_ptr();
+    info.need_fullpath = false;
+    info.block_extract = false;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    let info = unsafe { &mut *info };
+    info.geometry = RetroGameGeometry {
+        base_width: LORES.0 as c_uint,
+        base_height: LORES.1 as c_uint,
+        max_width: HIRES.0 as c_uint,
+        max_height: HIRES.1 as c_uint,
+        aspect_ratio: 2.0,
+    };
+    info.timing = RetroSystemTiming {
+        fps: FRAME_RATE as f64,
+        sample_rate: SAMPLE_RATE as f64,
+    };
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    with_core(|core| {
+        core.context.reset();
+        core.halted = false;
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let mut updated = false;
+    environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        (&raw mut updated).cast(),
+    );
+
+    let result = with_core(|core| {
+        if updated {
+            core.apply_options();
+        }
+        let callbacks = CALLBACKS.lock().unwrap();
+        core.run_frame(&callbacks)
+    });
+    // Logged after the callbacks are unlocked, since logging goes through the environment.
+    if let Some(Err(error)) = result {
+        log_error(error.as_ref());
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(|core| core.context.save_state_size()).unwrap_or(0)
+}
+
+/// # Safety
+/// `data` must be valid for writes of `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let Some(Ok(state)) = with_core(|core| core.context.save_state()) else {
+        return false;
+    };
+    if size < state.len() {
+        return false;
+    }
+    unsafe { ptr::copy_nonoverlapping(state.as_ptr(), data.cast(), state.len()) };
+    true
+}
+
+/// # Safety
+/// `data` must be valid for reads of `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let state = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+    with_core(|core| core.context.load_state(state).is_ok()).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {
+    with_core(|core| core.context.clear_cheats());
+}
+
+/// Codes use the syntax of cheat files without the state and name, e.g. `memory 3A1 03`.
+/// Several codes can be combined with `+`.
+///
+/// # Safety
+/// `code` must be null or point to a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+    if code.is_null() {
+        return;
+    }
+    let code = unsafe { CStr::from_ptr(code) }.to_string_lossy();
+    let name = format!("Cheat {index}");
+    with_core(|core| {
+        for effect in code
+            .split('+')
+            .filter_map(|part| part.parse::<CheatEffect>().ok())
+        {
+            let existing = core
+                .context
+                .cheats()
+                .iter()
+                .position(|cheat| cheat.name == name && cheat.effect == effect);
+            match existing {
+                Some(existing) => {
+                    core.context.set_cheat_enabled(existing, enabled);
+                }
+                None => {
+                    let mut cheat = Cheat::new(&name, effect);
+                    cheat.enabled = enabled;
+                    core.context.add_cheat(cheat);
+                }
+            }
+        }
+    });
+}
+
+/// # Safety
+/// `game` must be null or point to a valid `retro_game_info` whose data holds `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() {
+        return false;
+    }
+    let game = unsafe { &*game };
+    if game.data.is_null() {
+        return false;
+    }
+
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, (&raw mut format).cast()) {
+        return false;
+    }
+
+    let data = unsafe { slice::from_raw_parts(game.data.cast::<u8>(), game.size) }.to_vec();
+    let path = (!game.path.is_null()).then(|| unsafe { CStr::from_ptr(game.path) });
+    let extension = path
+        .and_then(|path| path.to_str().ok())
+        .and_then(|path| Path::new(path).extension())
+        .and_then(|extension| extension.to_str());
+    match Rom::parse(data, extension).and_then(Core::new) {
+        Ok(core) => {
+            CORE.set(Some(core));
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    CORE.set(None);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,88 @@
+use crate::{
+    Chip8Context, Quirks, TARGET_IPS,
+    frontend::{AudioSink, VideoSink},
+    pacing,
+    platform::Platform,
+    rom::RomError,
+};
+
+/// Configures a [`Chip8Context`] before it starts, see [`Chip8Context::builder`]. Without a video
+/// or audio sink the context runs headless.
+pub struct Chip8Builder {
+    rom: Vec<u8>,
+    platform: Platform,
+    quirks: Quirks,
+    ips: u32,
+    video: Option<Box<dyn VideoSink>>,
+    audio: Option<Box<dyn AudioSink>>,
+    instruction_cache: bool,
+}
+
+impl Chip8Builder {
+    pub(crate) fn new(rom: Vec<u8>) -> Self {
+        Chip8Builder {
+            rom,
+            platform: Platform::default(),
+            quirks: Quirks::default(),
+            ips: TARGET_IPS,
+            video: None,
+            audio: None,
+            instruction_cache: false,
+        }
+    }
+
+    /// The memory map to load the program into, [`Platform::CHIP_8`] by default.
+    pub const fn platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// See [`Chip8Context::set_quirks`].
+    pub const fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Instructions per second, see [`Chip8Context::set_ips`].
+    pub const fn ips(mut self, ips: u32) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    /// See [`Chip8Context::set_instructions_per_frame`].
+    pub const fn instructions_per_frame(mut self, instructions: u32) -> Self {
+        self.ips = instructions.saturating_mul(pacing::FRAME_RATE);
+        self
+    }
+
+    /// Presents the display through `video`, see [`Chip8Context::present`].
+    pub fn video(mut self, video: impl VideoSink + 'static) -> Self {
+        self.video = Some(Box::new(video));
+        self
+    }
+
+    /// Sounds the beeper through `audio`.
+    pub fn audio(mut self, audio: impl AudioSink + 'static) -> Self {
+        self.audio = Some(Box::new(audio));
+        self
+    }
+
+    /// See [`Chip8Context::enable_instruction_cache`].
+    pub const fn instruction_cache(mut self, enabled: bool) -> Self {
+        self.instruction_cache = enabled;
+        self
+    }
+
+    /// Fails if the program is empty or does not fit into the platform's program memory.
+    pub fn build(self) -> Result<Chip8Context, RomError> {
+        let mut context = Chip8Context::with_platform(self.rom, self.platform)?;
+        context.video = self.video;
+        context.audio = self.audio;
+        context.set_quirks(self.quirks);
+        context.set_ips(self.ips);
+        if self.instruction_cache {
+            context.enable_instruction_cache();
+        }
+        Ok(context)
+    }
+}
@@ -0,0 +1,54 @@
+/// How a byte must compare to its value at the previous snapshot to stay a candidate.
+#[derive(Clone, Copy)]
+pub enum SearchFilter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u8),
+}
+
+impl SearchFilter {
+    const fn matches(self, previous: u8, current: u8) -> bool {
+        match self {
+            SearchFilter::Equal => current == previous,
+            SearchFilter::Changed => current != previous,
+            SearchFilter::Increased => current > previous,
+            SearchFilter::Decreased => current < previous,
+            SearchFilter::Value(value) => current == value,
+        }
+    }
+}
+
+/// Narrows down the address of a value, such as a lives counter, by repeatedly comparing memory
+/// to a snapshot. Every address starts as a candidate.
+pub struct CheatSearch {
+    candidates: Vec<usize>,
+    snapshot: Vec<u8>,
+}
+
+impl CheatSearch {
+    pub fn new(memory: &[u8]) -> Self {
+        CheatSearch {
+            candidates: (0..memory.len()).collect(),
+            snapshot: memory.to_vec(),
+        }
+    }
+
+    /// Keeps the candidates matching `filter` and takes a new snapshot. Returns the number left.
+    pub fn filter(&mut self, memory: &[u8], filter: SearchFilter) -> usize {
+        self.candidates
+            .retain(|&address| filter.matches(self.snapshot[address], memory[address]));
+        self.snapshot.copy_from_slice(memory);
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// The value of `address` at the last snapshot.
+    pub fn snapshot_value(&self, address: usize) -> u8 {
+        self.snapshot[address % self.snapshot.len()]
+    }
+}
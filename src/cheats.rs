@@ -0,0 +1,222 @@
+use crate::{
+    Chip8Context,
+    hex::{parse_address, parse_word},
+    instruction::read_opcode,
+    watch::Register,
+};
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// What a cheat does while it is enabled.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CheatEffect {
+    /// Writes `value` to `address` every frame.
+    FreezeMemory { address: usize, value: u8 },
+    /// Sets the register to `value` every frame.
+    FreezeRegister { register: Register, value: u16 },
+    /// Replaces the instruction at `address` with `opcode`. The original is restored when disabled.
+    Patch { address: usize, opcode: u16 },
+}
+
+impl FromStr for CheatEffect {
+    type Err = String;
+
+    /// Parses `memory ADDRESS VALUE`, `register VX|I VALUE` or `patch ADDRESS OPCODE`, all in hex.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["memory", target, value] => Ok(CheatEffect::FreezeMemory {
+                address: parse_address(target)?,
+                value: u8::try_from(parse_word(value)?)
+                    .map_err(|_| format!("Value too large: {value}"))?,
+            }),
+            ["register", target, value] => {
+                let register: Register = target.parse()?;
+                let value = parse_word(value)?;
+                if register != Register::I && value > 0xFF {
+                    return Err(format!("Value too large: {value:X}"));
+                }
+                Ok(CheatEffect::FreezeRegister { register, value })
+            }
+            ["patch", target, opcode] => Ok(CheatEffect::Patch {
+                address: parse_address(target)?,
+                opcode: parse_word(opcode)?,
+            }),
+            _ => Err(format!("Invalid cheat: {text}")),
+        }
+    }
+}
+
+impl fmt::Display for CheatEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheatEffect::FreezeMemory { address, value } => {
+                write!(f, "memory {address:03X} {value:02X}")
+            }
+            CheatEffect::FreezeRegister {
+                register: Register::I,
+                value,
+            } => write!(f, "register I {value:03X}"),
+            CheatEffect::FreezeRegister { register, value } => {
+                write!(f, "register {register} {value:02X}")
+            }
+            CheatEffect::Patch { address, opcode } => write!(f, "patch {address:03X} {opcode:04X}"),
+        }
+    }
+}
+
+/// A named cheat. In a cheat file, every cheat is one line:
+///
+/// ```text
+/// # Comments and empty lines are ignored.
+/// on  memory   3A1 03   Infinite lives
+/// off register VF  00   No collisions
+/// on  patch    210 1230 Skip the intro
+/// ```
+pub struct Cheat {
+    pub name: String,
+    pub enabled: bool,
+    pub effect: CheatEffect,
+    /// The two bytes a patch replaced, while it is applied.
+    original: Option<[u8; 2]>,
+}
+
+impl Cheat {
+    pub fn new(name: &str, effect: CheatEffect) -> Self {
+        Cheat {
+            name: name.to_string(),
+            enabled: false,
+            effect,
+            original: None,
+        }
+    }
+
+    /// Parses one line of a cheat file. Returns `None` for comments and empty lines.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let enabled = match words[0] {
+            "on" => true,
+            "off" => false,
+            word => return Err(format!("Expected on or off, found {word}")),
+        };
+        let effect = words[1..words.len().min(4)].join(" ").parse()?;
+        Ok(Some(Cheat {
+            name: words.get(4..).unwrap_or_default().join(" "),
+            enabled,
+            effect,
+            original: None,
+        }))
+    }
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.enabled { "on " } else { "off" };
+        write!(f, "{state} {} {}", self.effect, self.name)
+    }
+}
+
+/// The cheat file belonging to a ROM, `game.cht` for `game.ch8`.
+pub fn cheat_path(rom_path: &Path) -> PathBuf {
+    rom_path.with_extension("cht")
+}
+
+/// Reads all cheats from a cheat file. Errors name the offending line.
+pub fn load_cheat_file(path: &Path) -> Result<Vec<Cheat>, Box<dyn Error>> {
+    let mut cheats = Vec::new();
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let cheat = Cheat::parse(line)
+            .map_err(|message| format!("{}:{}: {message}", path.display(), number + 1))?;
+        cheats.extend(cheat);
+    }
+    Ok(cheats)
+}
+
+impl Chip8Context {
+    /// Adds a cheat, applying it right away if it is enabled, and returns its index.
+    pub fn add_cheat(&mut self, cheat: Cheat) -> usize {
+        let enabled = cheat.enabled;
+        self.cheats.push(Cheat {
+            enabled: false,
+            ..cheat
+        });
+        let index = self.cheats.len() - 1;
+        self.set_cheat_enabled(index, enabled);
+        index
+    }
+
+    /// The cheats in the order they were added, enabled or not.
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Removes all cheats, restoring the instructions replaced by patches.
+    pub fn clear_cheats(&mut self) {
+        for index in 0..self.cheats.len() {
+            self.set_cheat_enabled(index, false);
+        }
+        self.cheats.clear();
+    }
+
+    /// Enables or disables the cheat at `index`. Returns `false` if there is no such cheat.
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        let Some(cheat) = self.cheats.get_mut(index) else {
+            return false;
+        };
+        cheat.enabled = enabled;
+        if !enabled
+            && let CheatEffect::Patch { address, .. } = cheat.effect
+            && let Some(original) = cheat.original.take()
+        {
+            self.write_memory(address, original[0]);
+            self.write_memory(address + 1, original[1]);
+        }
+        self.apply_cheats();
+        true
+    }
+
+    /// Flips the cheat at `index` and returns its new state.
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<bool> {
+        let enabled = !self.cheats.get(index)?.enabled;
+        self.set_cheat_enabled(index, enabled);
+        Some(enabled)
+    }
+
+    /// Applies all enabled cheats. Called once per frame.
+    pub(crate) fn apply_cheats(&mut self) {
+        for index in 0..self.cheats.len() {
+            let cheat = &self.cheats[index];
+            if !cheat.enabled {
+                continue;
+            }
+            match cheat.effect {
+                CheatEffect::FreezeMemory { address, value } => self.write_memory(address, value),
+                CheatEffect::FreezeRegister {
+                    register: Register::V(x),
+                    value,
+                } => self.register[x as usize] = value as u8,
+                CheatEffect::FreezeRegister {
+                    register: Register::I,
+                    value,
+                } => self.i = value & self.address_mask() as u16,
+                CheatEffect::Patch { address, opcode } => {
+                    if cheat.original.is_none() {
+                        let original = read_opcode(&self.memory, address & self.address_mask());
+                        self.cheats[index].original = Some(original.to_be_bytes());
+                    }
+                    let [high, low] = opcode.to_be_bytes();
+                    self.write_memory(address, high);
+                    self.write_memory(address + 1, low);
+                }
+            }
+        }
+    }
+}
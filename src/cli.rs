@@ -0,0 +1,218 @@
+use chip_8::{
+    cheats::cheat_path, hex::parse_address, platform::Platform, rom::RomFormat,
+    terminal::TerminalStyle,
+};
+use std::path::{Path, PathBuf};
+
+pub struct Options {
+    pub rom_path: String,
+    pub headless_instructions: Option<u64>,
+    pub screenshot: bool,
+    pub record_path: Option<PathBuf>,
+    pub record_audio: bool,
+    pub terminal: Option<TerminalStyle>,
+    pub profile: bool,
+    pub profile_path: Option<PathBuf>,
+    pub coverage_path: Option<PathBuf>,
+    pub trace_path: Option<PathBuf>,
+    // The debuggers and vsync only exist in the SDL window.
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub debug: bool,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub gdb_port: Option<u16>,
+    pub script_path: Option<PathBuf>,
+    pub cheats_path: Option<PathBuf>,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub vsync: bool,
+    pub instructions_per_frame: Option<u32>,
+    pub bench_instructions: Option<u64>,
+    pub decode_cache: bool,
+    pub platform: Option<Platform>,
+    pub load_address: Option<usize>,
+    pub entry_point: Option<usize>,
+    pub stack_depth: Option<usize>,
+}
+
+const DEFAULT_BENCH_INSTRUCTIONS: u64 = 100_000_000;
+
+impl Options {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut headless_instructions = None;
+        let mut screenshot = false;
+        let mut record_path = None;
+        let mut record_audio = false;
+        let mut terminal = None;
+        let mut profile = false;
+        let mut profile_path = None;
+        let mut coverage_path = None;
+        let mut trace_path = None;
+        let mut debug = false;
+        let mut gdb_port = None;
+        let mut script_path = None;
+        let mut cheats_path = None;
+        let mut vsync = false;
+        let mut instructions_per_frame = None;
+        let mut bench_instructions = None;
+        let mut decode_cache = false;
+        let mut platform = None;
+        let mut load_address = None;
+        let mut entry_point = None;
+        let mut stack_depth = None;
+
+        let mut args = args.peekable();
+        if args.next_if(|arg| arg == "bench").is_some() {
+            bench_instructions = Some(DEFAULT_BENCH_INSTRUCTIONS);
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--screenshot-after" | "--headless" => {
+                    let value = args
+                        .next()
+                        .ok_or(format!("{arg} needs a number of instructions"))?;
+                    let count = value
+                        .parse()
+                        .map_err(|_| format!("Invalid instruction count: {value}"))?;
+                    headless_instructions = Some(count);
+                    screenshot |= arg == "--screenshot-after";
+                }
+                "--record" => {
+                    let path = args.next().ok_or("--record needs an output file")?;
+                    record_path = Some(PathBuf::from(path));
+                }
+                "--record-audio" => record_audio = true,
+                "--terminal" => terminal = Some(TerminalStyle::HalfBlock),
+                "--braille" => terminal = Some(TerminalStyle::Braille),
+                "--profile" => profile = true,
+                "--debug" => debug = true,
+                "--vsync" => vsync = true,
+                "--decode-cache" => decode_cache = true,
+                "--ipf" => {
+                    let value = args
+                        .next()
+                        .ok_or("--ipf needs a number of instructions per frame")?;
+                    let count = value
+                        .parse()
+                        .map_err(|_| format!("Invalid instructions per frame: {value}"))?;
+                    instructions_per_frame = Some(count);
+                }
+                "--instructions" if bench_instructions.is_some() => {
+                    let value = args
+                        .next()
+                        .ok_or("--instructions needs a number of instructions")?;
+                    let count = value
+                        .parse()
+                        .map_err(|_| format!("Invalid instruction count: {value}"))?;
+                    bench_instructions = Some(count);
+                }
+                "--gdb" => {
+                    let value = args.next().ok_or("--gdb needs a port")?;
+                    let port = value
+                        .parse()
+                        .map_err(|_| format!("Invalid port: {value}"))?;
+                    gdb_port = Some(port);
+                }
+                "--platform" => {
+                    let name = args.next().ok_or("--platform needs a platform name")?;
+                    let names: Vec<_> = Platform::ALL.iter().map(|p| p.name).collect();
+                    platform = Some(Platform::from_name(&name).ok_or(format!(
+                        "Unknown platform: {name}, expected one of {}",
+                        names.join(", ")
+                    ))?);
+                }
+                "--load-address" => {
+                    let value = args.next().ok_or("--load-address needs an address")?;
+                    load_address = Some(parse_address(&value)?);
+                }
+                "--entry" => {
+                    let value = args.next().ok_or("--entry needs an address")?;
+                    entry_point = Some(parse_address(&value)?);
+                }
+                "--stack-depth" => {
+                    let value = args
+                        .next()
+                        .ok_or("--stack-depth needs a number of nested calls")?;
+                    let depth = value
+                        .parse()
+                        .map_err(|_| format!("Invalid stack depth: {value}"))?;
+                    stack_depth = Some(depth);
+                }
+                "--coverage" => {
+                    let path = args.next().ok_or("--coverage needs an output file")?;
+                    coverage_path = Some(PathBuf::from(path));
+                }
+                "--script" => {
+                    let path = args.next().ok_or("--script needs a script file")?;
+                    script_path = Some(PathBuf::from(path));
+                }
+                "--cheats" => {
+                    let path = args.next().ok_or("--cheats needs a cheat file")?;
+                    cheats_path = Some(PathBuf::from(path));
+                }
+                "--trace" => {
+                    let path = args.next().ok_or("--trace needs an output file")?;
+                    trace_path = Some(PathBuf::from(path));
+                }
+                "--profile-out" => {
+                    let path = args.next().ok_or("--profile-out needs an output file")?;
+                    profile = true;
+                    profile_path = Some(PathBuf::from(path));
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: {arg}")),
+                _ => rom_path = Some(arg),
+            }
+        }
+
+        Ok(Options {
+            rom_path: rom_path.ok_or("No CHIP-8 file supplied as an argument!")?,
+            headless_instructions,
+            screenshot,
+            record_path,
+            record_audio,
+            terminal,
+            profile,
+            profile_path,
+            coverage_path,
+            trace_path,
+            debug,
+            gdb_port,
+            script_path,
+            cheats_path,
+            vsync,
+            instructions_per_frame,
+            bench_instructions,
+            decode_cache,
+            platform,
+            load_address,
+            entry_point,
+            stack_depth,
+        })
+    }
+
+    /// The platform given with `--platform`, or the usual one for `format`, with the addresses
+    /// given with `--load-address` and `--entry` and the depth given with `--stack-depth`.
+    pub fn platform(&self, format: RomFormat) -> Platform {
+        let mut platform = self
+            .platform
+            .unwrap_or_else(|| Platform::for_format(format));
+        if let Some(address) = self.load_address {
+            platform = platform.with_load_address(address);
+        }
+        if let Some(address) = self.entry_point {
+            platform = platform.with_entry_point(address);
+        }
+        if let Some(depth) = self.stack_depth {
+            platform = platform.with_stack_depth(depth);
+        }
+        platform
+    }
+
+    /// The cheat file given with `--cheats`, or the one next to the ROM.
+    pub fn cheat_file(&self) -> PathBuf {
+        match &self.cheats_path {
+            Some(path) => path.clone(),
+            None => cheat_path(Path::new(&self.rom_path)),
+        }
+    }
+}
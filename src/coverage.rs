@@ -0,0 +1,119 @@
+use crate::{disassembler::mnemonic, instruction::read_opcode};
+use std::{error::Error, fmt::Write as _, fs, path::Path};
+
+/// Tracks which bytes of the program were executed as code and which were accessed as data
+/// through the index register (`DXYN`, `FX33`, `FX55` and `FX65`).
+pub struct Coverage {
+    start: usize,
+    end: usize,
+    executed: Vec<u64>,
+    data: Vec<u64>,
+}
+
+impl Coverage {
+    /// Covers the program loaded at `start` with a length of `len` bytes, in `memory_size` bytes
+    /// of memory.
+    pub fn new(start: usize, len: usize, memory_size: usize) -> Self {
+        Coverage {
+            start,
+            end: (start + len).min(memory_size),
+            executed: vec![0; memory_size],
+            data: vec![0; memory_size],
+        }
+    }
+
+    pub(crate) fn record_instruction(&mut self, address: usize) {
+        self.executed[address] += 1;
+    }
+
+    pub(crate) fn record_data(&mut self, start: usize, len: usize) {
+        for address in start..(start + len).min(self.data.len()) {
+            self.data[address] += 1;
+        }
+    }
+
+    fn is_code(&self, address: usize) -> bool {
+        self.executed[address] > 0 || (address > 0 && self.executed[address - 1] > 0)
+    }
+
+    /// One character per program byte, 32 per line: `C` code, `D` data, `B` both, `.` untouched.
+    pub fn map(&self) -> String {
+        let mut map = String::new();
+        let (mut code, mut data, mut untouched) = (0, 0, 0);
+        for line_start in (self.start..self.end).step_by(32) {
+            let _ = write!(map, "{line_start:#05X}  ");
+            for address in line_start..(line_start + 32).min(self.end) {
+                let symbol = match (self.is_code(address), self.data[address] > 0) {
+                    (true, true) => 'B',
+                    (true, false) => 'C',
+                    (false, true) => 'D',
+                    (false, false) => '.',
+                };
+                match symbol {
+                    'C' | 'B' => code += 1,
+                    'D' => data += 1,
+                    _ => untouched += 1,
+                }
+                map.push(symbol);
+            }
+            map.push('\n');
+        }
+
+        let total = (self.end - self.start).max(1) as f64;
+        let summary = format!(
+            "Code: {code} bytes ({:.1}%), data: {data} bytes ({:.1}%), untouched: {untouched} bytes ({:.1}%)\n\n",
+            100.0 * code as f64 / total,
+            100.0 * data as f64 / total,
+            100.0 * untouched as f64 / total
+        );
+        summary + map.as_str()
+    }
+
+    /// Disassembly of the program with hit counts. Data bytes are shown as sprite rows and
+    /// untouched ranges are collapsed into a single line.
+    pub fn listing(&self, memory: &[u8]) -> String {
+        let mut listing = String::new();
+        let mut address = self.start;
+        while address < self.end {
+            if self.executed[address] > 0 {
+                let opcode = read_opcode(memory, address);
+                let _ = writeln!(
+                    listing,
+                    "{address:#05X}  {opcode:04X}      {:<18} ; hits: {}",
+                    mnemonic(opcode),
+                    self.executed[address]
+                );
+                address += 2;
+            } else if self.data[address] > 0 {
+                let byte = memory[address];
+                let sprite: String = (0..8)
+                    .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                    .collect();
+                let _ = writeln!(
+                    listing,
+                    "{address:#05X}  {byte:02X}        {sprite:<18} ; data, accesses: {}",
+                    self.data[address]
+                );
+                address += 1;
+            } else {
+                let untouched_end = (address..self.end)
+                    .find(|&a| self.executed[a] > 0 || self.data[a] > 0)
+                    .unwrap_or(self.end);
+                let _ = writeln!(
+                    listing,
+                    "{address:#05X}  ..        {:<18} ; never touched ({} bytes)",
+                    "",
+                    untouched_end - address
+                );
+                address = untouched_end;
+            }
+        }
+        listing
+    }
+
+    /// Writes the coverage map followed by the annotated listing to `path`.
+    pub fn export(&self, path: &Path, memory: &[u8]) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.map() + "\n" + self.listing(memory).as_str())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    Chip8Context,
+    cheat_search::{CheatSearch, SearchFilter},
+    cheats::{Cheat, CheatEffect},
+    disassembler::mnemonic,
+    hex::{parse_address, parse_byte},
+    watch::{Register, WatchHit, Watchpoint},
+};
+use std::{
+    error::Error,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+const HELP: &str = "\
+Commands:
+  watch read|write|access|fetch START [END]   stop when memory in START..=END is accessed
+  watch V0-VF|I                               stop when a register changes
+  unwatch N                                   remove watchpoint N
+  watches                                     list watchpoints
+  pause, continue (c), step (s) [COUNT]       control execution
+  regs (r)                                    show the next instruction and the registers
+  search start                                snapshot memory to search for a value
+  search equal|changed|increased|decreased    keep addresses that compare so to the last snapshot
+  search value VALUE                          keep addresses holding VALUE
+  search list                                 show the remaining addresses
+  cheat ADDRESS [VALUE] [NAME]                freeze ADDRESS at VALUE (default: its current value)
+                                              and append the cheat to the cheat file
+  cheats                                      list the cheats
+Addresses and values are hexadecimal, with or without a 0x prefix.";
+
+/// A command line debugger that reads commands from standard input while the emulator keeps
+/// running, so the window stays responsive.
+pub struct Debugger {
+    commands: Receiver<String>,
+    cheat_path: PathBuf,
+    search: Option<CheatSearch>,
+}
+
+impl Debugger {
+    /// Cheats found with the `cheat` command are appended to `cheat_path`.
+    pub fn new(cheat_path: PathBuf) -> Self {
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        println!("Debugger ready, type `help` for a list of commands");
+        Debugger {
+            commands,
+            cheat_path,
+            search: None,
+        }
+    }
+
+    /// Runs the commands entered since the last call.
+    pub fn poll(&mut self, context: &mut Chip8Context, paused: &mut bool) {
+        while let Ok(line) = self.commands.try_recv() {
+            if let Err(error) = self.execute(&line, context, paused) {
+                println!("{error}");
+            }
+        }
+    }
+
+    /// Pauses and shows the triggering instruction if the last step hit a watchpoint.
+    pub fn check_watchpoints(&self, context: &mut Chip8Context, paused: &mut bool) -> bool {
+        let Some(hit) = context.take_watch_hit() else {
+            return false;
+        };
+        *paused = true;
+        print_hit(&hit, context);
+        true
+    }
+
+    fn execute(
+        &mut self,
+        line: &str,
+        context: &mut Chip8Context,
+        paused: &mut bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => (),
+            ["help" | "h"] => println!("{HELP}"),
+            ["watch", register] if register.parse::<Register>().is_ok() => {
+                let watchpoint = Watchpoint::Register(register.parse()?);
+                let index = context.add_watchpoint(watchpoint.clone());
+                println!("Watchpoint {index}: {watchpoint}");
+            }
+            ["watch", kind, start, rest @ ..] if rest.len() <= 1 => {
+                let (read, write, fetch) = match *kind {
+                    "read" => (true, false, false),
+                    "write" => (false, true, false),
+                    "access" => (true, true, false),
+                    "fetch" => (false, false, true),
+                    _ => return Err(format!("Unknown access kind: {kind}").into()),
+                };
+                let start = parse_address(start)?;
+                let end = match rest {
+                    [end] => parse_address(end)?,
+                    _ => start,
+                };
+                if end < start {
+                    return Err("The end address is before the start address".into());
+                }
+                let watchpoint = Watchpoint::Memory {
+                    start,
+                    end,
+                    read,
+                    write,
+                    fetch,
+                };
+                let index = context.add_watchpoint(watchpoint.clone());
+                println!("Watchpoint {index}: {watchpoint}");
+            }
+            ["unwatch", index] => {
+                let index: usize = index.parse()?;
+                match context.remove_watchpoint(index) {
+                    Some(watchpoint) => println!("Removed watchpoint {index}: {watchpoint}"),
+                    None => return Err(format!("No watchpoint {index}").into()),
+                }
+            }
+            ["watches"] => {
+                if context.watchpoints().is_empty() {
+                    println!("No watchpoints");
+                }
+                for (index, watchpoint) in context.watchpoints().iter().enumerate() {
+                    println!("{index}: {watchpoint}");
+                }
+            }
+            ["pause"] => {
+                *paused = true;
+                println!("{}", context.trace_line());
+            }
+            ["continue" | "c"] => *paused = false,
+            ["step" | "s", rest @ ..] if rest.len() <= 1 => {
+                let count: u64 = match rest {
+                    [count] => count.parse()?,
+                    _ => 1,
+                };
+                *paused = true;
+                for _ in 0..count {
+                    context.step()?;
+                    if self.check_watchpoints(context, paused) {
+                        break;
+                    }
+                }
+                println!("{}", context.trace_line());
+            }
+            ["regs" | "r"] => println!("{}", context.trace_line()),
+            ["search", "start"] => {
+                self.search = Some(CheatSearch::new(context.memory()));
+                println!("Took a snapshot of {} addresses", context.memory().len());
+            }
+            ["search", "list"] => {
+                let search = self
+                    .search
+                    .as_ref()
+                    .ok_or("No search, use `search start`")?;
+                for &address in search.candidates().iter().take(32) {
+                    println!(
+                        "{address:#05X}  {:02X} (was {:02X})",
+                        context.memory()[address],
+                        search.snapshot_value(address)
+                    );
+                }
+                if search.candidates().len() > 32 {
+                    println!("... {} more", search.candidates().len() - 32);
+                }
+            }
+            ["search", filter @ ..] => {
+                let filter = match filter {
+                    ["equal"] => SearchFilter::Equal,
+                    ["changed"] => SearchFilter::Changed,
+                    ["increased"] => SearchFilter::Increased,
+                    ["decreased"] => SearchFilter::Decreased,
+                    ["value", value] => SearchFilter::Value(parse_byte(value)?),
+                    _ => return Err(format!("Unknown search: {line}").into()),
+                };
+                let search = self
+                    .search
+                    .as_mut()
+                    .ok_or("No search, use `search start`")?;
+                let left = search.filter(context.memory(), filter);
+                println!("{left} addresses left");
+            }
+            ["cheat", address, rest @ ..] => {
+                let address = parse_address(address)?;
+                let current = *context
+                    .memory()
+                    .get(address)
+                    .ok_or(format!("Address outside of memory: {address:03X}"))?;
+                let (value, name) = match rest {
+                    [value, name @ ..] => (parse_byte(value)?, name.join(" ")),
+                    [] => (current, String::new()),
+                };
+                let name = if name.is_empty() {
+                    format!("Freeze {address:03X}")
+                } else {
+                    name
+                };
+                let mut cheat = Cheat::new(&name, CheatEffect::FreezeMemory { address, value });
+                cheat.enabled = true;
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.cheat_path)?;
+                writeln!(file, "{cheat}")?;
+                println!("{cheat}");
+                println!("Added to {}", self.cheat_path.display());
+                context.add_cheat(cheat);
+            }
+            ["cheats"] => {
+                if context.cheats().is_empty() {
+                    println!("No cheats");
+                }
+                for (index, cheat) in context.cheats().iter().enumerate() {
+                    println!("{index}: {cheat}");
+                }
+            }
+            _ => return Err(format!("Unknown command: {line}, type `help` for a list").into()),
+        }
+        Ok(())
+    }
+}
+
+fn print_hit(hit: &WatchHit, context: &Chip8Context) {
+    let watchpoint = context
+        .watchpoints()
+        .get(hit.watchpoint)
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    println!(
+        "Watchpoint {} ({watchpoint}) hit: {}",
+        hit.watchpoint, hit.detail
+    );
+    println!(
+        "  {:#05X}  {:04X}  {}",
+        hit.address,
+        hit.opcode,
+        mnemonic(hit.opcode)
+    );
+    println!("{}", context.trace_line());
+}
@@ -0,0 +1,99 @@
+/// Returns the opcode pattern an instruction belongs to, e.g. `"8XY4"` for `0x8124`.
+pub const fn opcode_class(opcode: u16) -> &'static str {
+    match opcode >> 12 {
+        0x0 => match opcode {
+            0x00E0 => "00E0",
+            0x00EE => "00EE",
+            _ => "0NNN",
+        },
+        0x1 => "1NNN",
+        0x2 => "2NNN",
+        0x3 => "3XNN",
+        0x4 => "4XNN",
+        0x5 => "5XY0",
+        0x6 => "6XNN",
+        0x7 => "7XNN",
+        0x8 => match opcode & 0xF {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "invalid",
+        },
+        0x9 => "9XY0",
+        0xA => "ANNN",
+        0xB => "BNNN",
+        0xC => "CXNN",
+        0xD => "DXYN",
+        0xE => match opcode & 0xFF {
+            0x9E => "EX9E",
+            0xA1 => "EXA1",
+            _ => "invalid",
+        },
+        _ => match opcode & 0xFF {
+            0x07 => "FX07",
+            0x0A => "FX0A",
+            0x15 => "FX15",
+            0x18 => "FX18",
+            0x1E => "FX1E",
+            0x29 => "FX29",
+            0x33 => "FX33",
+            0x55 => "FX55",
+            0x65 => "FX65",
+            _ => "invalid",
+        },
+    }
+}
+
+/// Returns the assembly for an instruction, e.g. `"ADD V1, V2"` for `0x8124`.
+pub fn mnemonic(opcode: u16) -> String {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let nn = opcode & 0xFF;
+    let nnn = opcode & 0xFFF;
+
+    match opcode_class(opcode) {
+        "00E0" => "CLS".to_string(),
+        "00EE" => "RET".to_string(),
+        "0NNN" => format!("SYS {nnn:#05X}"),
+        "1NNN" => format!("JP {nnn:#05X}"),
+        "2NNN" => format!("CALL {nnn:#05X}"),
+        "3XNN" => format!("SE V{x:X}, {nn:#04X}"),
+        "4XNN" => format!("SNE V{x:X}, {nn:#04X}"),
+        "5XY0" => format!("SE V{x:X}, V{y:X}"),
+        "6XNN" => format!("LD V{x:X}, {nn:#04X}"),
+        "7XNN" => format!("ADD V{x:X}, {nn:#04X}"),
+        "8XY0" => format!("LD V{x:X}, V{y:X}"),
+        "8XY1" => format!("OR V{x:X}, V{y:X}"),
+        "8XY2" => format!("AND V{x:X}, V{y:X}"),
+        "8XY3" => format!("XOR V{x:X}, V{y:X}"),
+        "8XY4" => format!("ADD V{x:X}, V{y:X}"),
+        "8XY5" => format!("SUB V{x:X}, V{y:X}"),
+        "8XY6" => format!("SHR V{x:X}, V{y:X}"),
+        "8XY7" => format!("SUBN V{x:X}, V{y:X}"),
+        "8XYE" => format!("SHL V{x:X}, V{y:X}"),
+        "9XY0" => format!("SNE V{x:X}, V{y:X}"),
+        "ANNN" => format!("LD I, {nnn:#05X}"),
+        "BNNN" => format!("JP V0, {nnn:#05X}"),
+        "CXNN" => format!("RND V{x:X}, {nn:#04X}"),
+        "DXYN" => format!("DRW V{x:X}, V{y:X}, {n}"),
+        "EX9E" => format!("SKP V{x:X}"),
+        "EXA1" => format!("SKNP V{x:X}"),
+        "FX07" => format!("LD V{x:X}, DT"),
+        "FX0A" => format!("LD V{x:X}, K"),
+        "FX15" => format!("LD DT, V{x:X}"),
+        "FX18" => format!("LD ST, V{x:X}"),
+        "FX1E" => format!("ADD I, V{x:X}"),
+        "FX29" => format!("LD F, V{x:X}"),
+        "FX33" => format!("LD B, V{x:X}"),
+        "FX55" => format!("LD [I], V{x:X}"),
+        "FX65" => format!("LD V{x:X}, [I]"),
+        _ => format!("DW {opcode:#06X}"),
+    }
+}
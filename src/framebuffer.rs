@@ -0,0 +1,85 @@
+/// Width and height of the standard CHIP-8 display.
+pub const LORES: (usize, usize) = (64, 32);
+/// Width and height of the SUPER-CHIP high resolution display, the largest supported.
+pub const HIRES: (usize, usize) = (128, 64);
+
+/// A monochrome display with one bit per pixel. Every row is a `u128` with the leftmost pixel in
+/// the most significant bit, so a sprite row is drawn with a single XOR.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer {
+    rows: [u128; HIRES.1],
+    width: usize,
+    height: usize,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new(LORES.0, LORES.1)
+    }
+}
+
+impl Framebuffer {
+    /// Creates a blank display; `width` and `height` are limited to [`HIRES`].
+    pub const fn new(width: usize, height: usize) -> Self {
+        assert!(width <= HIRES.0 && height <= HIRES.1);
+        Framebuffer {
+            rows: [0; HIRES.1],
+            width,
+            height,
+        }
+    }
+
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    pub const fn clear(&mut self) {
+        self.rows = [0; HIRES.1];
+    }
+
+    pub const fn pixel(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << (127 - x)) != 0
+    }
+
+    /// The pixels of row `y`, the leftmost in the most significant bit.
+    pub const fn row(&self, y: usize) -> u128 {
+        self.rows[y]
+    }
+
+    /// Replaces row `y`; pixels beyond the width are dropped.
+    pub const fn set_row(&mut self, y: usize, pixels: u128) {
+        self.rows[y] = pixels & self.row_mask();
+    }
+
+    /// XORs the 8 pixels of a sprite row onto the display with its left edge at `x`, clipping
+    /// what falls off the right edge. Returns whether a lit pixel was turned off.
+    pub const fn draw_sprite_row(&mut self, x: usize, y: usize, sprite: u8) -> bool {
+        let pixels = ((sprite as u128) << 120 >> x) & self.row_mask();
+        let collision = self.rows[y] & pixels != 0;
+        self.rows[y] ^= pixels;
+        collision
+    }
+
+    /// The pixels of every row, from left to right.
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool>> {
+        (0..self.height).map(move |y| (0..self.width).map(move |x| self.pixel(x, y)))
+    }
+
+    /// The coordinates of every lit pixel.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.pixel(x, y))
+    }
+
+    const fn row_mask(&self) -> u128 {
+        match u128::MAX.checked_shr(self.width as u32) {
+            Some(unused) => !unused,
+            None => u128::MAX,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::framebuffer::Framebuffer;
+use std::error::Error;
+
+/// Shows the display, e.g. in a window or a terminal.
+pub trait VideoSink {
+    /// Shows `display`. `changed` is false if it is the same as in the previous call, in which
+    /// case sinks may skip the frame.
+    fn present(&mut self, display: &Framebuffer, changed: bool) -> Result<(), Box<dyn Error>>;
+
+    /// Sets the title of the window, if there is one.
+    fn set_title(&mut self, _title: &str) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Plays the beeper.
+pub trait AudioSink {
+    /// Called at 60 Hz with whether the sound timer is running.
+    fn set_beeping(&mut self, beeping: bool) -> Result<(), Box<dyn Error>>;
+}
+
+/// Reads the keypad, e.g. from a keyboard or a gamepad.
+pub trait InputSource {
+    /// The held keys, indexed by keypad key (0x0 - 0xF).
+    fn keypad(&mut self) -> [bool; 16];
+}
+
+/// The keyboard keys standing in for the COSMAC VIP's 4x4 keypad, whose rows read 123C, 456D,
+/// 789E and A0BF: 1234/QWER/ASDF/ZXCV on a QWERTY keyboard, with `Y` doubling as `Z` for QWERTZ
+/// keyboards.
+const KEYPAD_LAYOUT: [(char, u8); 17] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('y', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+/// The keypad as pressed through the keyboard. Every frontend maps its keys through this so they
+/// share one layout. A keypad key stays down while any keyboard key mapped to it is held.
+#[derive(Default)]
+pub struct KeyboardKeypad {
+    held: [bool; KEYPAD_LAYOUT.len()],
+}
+
+impl KeyboardKeypad {
+    /// Presses or releases the keyboard key labelled `c`, ignoring case. Returns the keypad key it
+    /// is mapped to and whether that keypad key is now down.
+    pub fn key_event(&mut self, c: char, pressed: bool) -> Option<(u8, bool)> {
+        let c = c.to_ascii_lowercase();
+        let index = KEYPAD_LAYOUT.iter().position(|&(label, _)| label == c)?;
+        self.held[index] = pressed;
+        let key = KEYPAD_LAYOUT[index].1;
+        let down = KEYPAD_LAYOUT
+            .iter()
+            .zip(self.held)
+            .any(|(&(_, mapped), held)| mapped == key && held);
+        Some((key, down))
+    }
+
+    /// Releases every key, e.g. when releases can no longer be reported.
+    pub fn release_all(&mut self) {
+        self.held = [false; KEYPAD_LAYOUT.len()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad_keys_stay_down_until_all_their_keyboard_keys_are_released() {
+        let mut keyboard = KeyboardKeypad::default();
+        assert_eq!(keyboard.key_event('1', true), Some((0x1, true)));
+        assert_eq!(keyboard.key_event('Z', true), Some((0xA, true)));
+        assert_eq!(keyboard.key_event('y', true), Some((0xA, true)));
+        assert_eq!(keyboard.key_event('y', false), Some((0xA, true)));
+        assert_eq!(keyboard.key_event('z', false), Some((0xA, false)));
+        assert_eq!(keyboard.key_event('x', true), Some((0x0, true)));
+        assert_eq!(keyboard.key_event('9', true), None);
+    }
+}
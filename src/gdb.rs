@@ -0,0 +1,477 @@
+use crate::{Chip8Context, stack::StackError, watch::Watchpoint};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt::Write as _,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    ops::Range,
+};
+
+/// Size of the `g` packet: V0-VF, I, PC, SP (the stack depth), DT and ST, all little endian.
+const REGISTERS_SIZE: usize = 23;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// A GDB remote serial protocol server on a localhost TCP port. The emulator pauses when GDB
+/// connects and runs only while GDB lets it continue, stopping at software breakpoints (`Z0`/`Z1`)
+/// and watchpoints (`Z2`-`Z4`).
+pub struct GdbStub {
+    listener: TcpListener,
+    connection: Option<TcpStream>,
+    input: Vec<u8>,
+    breakpoints: BTreeSet<usize>,
+    // Watchpoints GDB added to the context, removed again when it disconnects.
+    watchpoints: Vec<Watchpoint>,
+    running: bool,
+    killed: bool,
+}
+
+impl GdbStub {
+    pub fn new(port: u16) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        println!("Waiting for GDB on 127.0.0.1:{port}");
+        Ok(GdbStub {
+            listener,
+            connection: None,
+            input: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            running: false,
+            killed: false,
+        })
+    }
+
+    /// Accepts a connection and handles the packets received since the last call.
+    /// Returns `false` once GDB asked to kill the program.
+    pub fn poll(
+        &mut self,
+        context: &mut Chip8Context,
+        paused: &mut bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        let Some(connection) = &mut self.connection else {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    println!("GDB connected from {address}");
+                    self.connection = Some(stream);
+                    self.running = false;
+                    *paused = true;
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => (),
+                Err(error) => return Err(error.into()),
+            }
+            return Ok(true);
+        };
+
+        let mut buffer = [0; 4096];
+        loop {
+            match connection.read(&mut buffer) {
+                Ok(0) => {
+                    self.disconnect(context, paused);
+                    return Ok(true);
+                }
+                Ok(len) => self.input.extend_from_slice(&buffer[..len]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        while let Some(&first) = self.input.first() {
+            match first {
+                // Interrupt, sent by GDB on Ctrl-C.
+                0x03 => {
+                    self.input.remove(0);
+                    if self.running {
+                        self.running = false;
+                        *paused = true;
+                        self.send("S02")?;
+                    }
+                }
+                b'$' => {
+                    let Some(end) = self.input.iter().position(|&b| b == b'#') else {
+                        break;
+                    };
+                    if self.input.len() < end + 3 {
+                        break;
+                    }
+                    let packet = String::from_utf8_lossy(&self.input[1..end]).into_owned();
+                    self.input.drain(..end + 3);
+                    self.write(b"+")?;
+                    if let Some(reply) = self.handle(&packet, context, paused)? {
+                        self.send(&reply)?;
+                    }
+                    if self.connection.is_none() {
+                        break;
+                    }
+                }
+                // Acknowledgements and anything outside of a packet.
+                _ => {
+                    self.input.remove(0);
+                }
+            }
+        }
+        Ok(!self.killed)
+    }
+
+    /// Stops at breakpoints and watchpoints while GDB lets the program run. Call after every step.
+    pub fn check_stop(
+        &mut self,
+        context: &mut Chip8Context,
+        paused: &mut bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.running {
+            return Ok(());
+        }
+        let reply = if let Some(hit) = context.take_watch_hit() {
+            stop_reply_for_watchpoint(context.watchpoints().get(hit.watchpoint))
+        } else if self.breakpoints.contains(&context.program_counter) {
+            "T05swbreak:;".to_string()
+        } else {
+            return Ok(());
+        };
+        self.running = false;
+        *paused = true;
+        self.send(&reply)
+    }
+
+    /// Stops with a signal when an instruction fails while GDB lets the program run, instead of
+    /// ending the emulator. Returns the error if GDB is not attached.
+    pub fn stop_on_error(
+        &mut self,
+        error: Box<dyn Error>,
+        paused: &mut bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.running {
+            return Err(error);
+        }
+        println!("{error}");
+        self.running = false;
+        *paused = true;
+        self.send(&stop_reply_for_error(error.as_ref()))
+    }
+
+    fn handle(
+        &mut self,
+        packet: &str,
+        context: &mut Chip8Context,
+        paused: &mut bool,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let reply = match packet.as_bytes().first() {
+            Some(b'?') => "S05".to_string(),
+            Some(b'g') => to_hex(&read_registers(context)),
+            Some(b'G') => match from_hex(&packet[1..]) {
+                Some(bytes) if bytes.len() == REGISTERS_SIZE => {
+                    write_registers(context, &bytes);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            Some(b'p') => match usize::from_str_radix(&packet[1..], 16)
+                .ok()
+                .and_then(register_layout)
+            {
+                Some((offset, size)) => to_hex(&read_registers(context)[offset..offset + size]),
+                None => "E01".to_string(),
+            },
+            Some(b'P') => match parse_register_write(&packet[1..]) {
+                Some(((offset, size), value)) if value.len() == size => {
+                    let mut bytes = read_registers(context);
+                    bytes[offset..offset + size].copy_from_slice(&value);
+                    write_registers(context, &bytes);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            Some(b'm') => match memory_range(&packet[1..], context.memory.len()) {
+                Some(range) => to_hex(&context.memory[range]),
+                None => "E14".to_string(),
+            },
+            Some(b'M') => {
+                let (range, data) = packet[1..].split_once(':').unwrap_or_default();
+                match (memory_range(range, context.memory.len()), from_hex(data)) {
+                    (Some(range), Some(bytes)) if bytes.len() == range.len() => {
+                        for (address, byte) in range.zip(bytes) {
+                            context.write_memory(address, byte);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => "E14".to_string(),
+                }
+            }
+            Some(b'c') => {
+                if let Ok(address) = usize::from_str_radix(&packet[1..], 16) {
+                    context.program_counter = address & context.address_mask();
+                }
+                self.running = true;
+                *paused = false;
+                return Ok(None);
+            }
+            Some(b's') => {
+                if let Ok(address) = usize::from_str_radix(&packet[1..], 16) {
+                    context.program_counter = address & context.address_mask();
+                }
+                match context.step() {
+                    Err(error) => {
+                        println!("{error}");
+                        stop_reply_for_error(error.as_ref())
+                    }
+                    Ok(()) => match context.take_watch_hit() {
+                        Some(hit) => {
+                            stop_reply_for_watchpoint(context.watchpoints().get(hit.watchpoint))
+                        }
+                        None => "S05".to_string(),
+                    },
+                }
+            }
+            Some(b'Z' | b'z') => self.set_stop_point(packet, context),
+            Some(b'D') => {
+                self.send("OK")?;
+                self.disconnect(context, paused);
+                return Ok(None);
+            }
+            Some(b'k') => {
+                self.killed = true;
+                self.disconnect(context, paused);
+                return Ok(None);
+            }
+            Some(b'H') => "OK".to_string(),
+            Some(b'q') => query(packet),
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    /// Handles `Z`/`z` packets: `type,address,kind`.
+    fn set_stop_point(&mut self, packet: &str, context: &mut Chip8Context) -> String {
+        let insert = packet.starts_with('Z');
+        let mut fields = packet[1..].split(',');
+        let (Some(kind), Some(address), Some(len)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return "E01".to_string();
+        };
+        let (Ok(address), Ok(len)) = (
+            usize::from_str_radix(address, 16),
+            usize::from_str_radix(len, 16),
+        ) else {
+            return "E01".to_string();
+        };
+        if address > context.address_mask() {
+            return "E14".to_string();
+        }
+
+        let (read, write) = match kind {
+            "0" | "1" => {
+                if insert {
+                    self.breakpoints.insert(address);
+                } else {
+                    self.breakpoints.remove(&address);
+                }
+                return "OK".to_string();
+            }
+            "2" => (false, true),
+            "3" => (true, false),
+            "4" => (true, true),
+            _ => return String::new(),
+        };
+        let watchpoint = Watchpoint::Memory {
+            start: address,
+            end: (address + len.max(1) - 1).min(context.address_mask()),
+            read,
+            write,
+            fetch: false,
+        };
+        if insert {
+            context.add_watchpoint(watchpoint.clone());
+            self.watchpoints.push(watchpoint);
+        } else if let Some(index) = self.watchpoints.iter().position(|w| *w == watchpoint) {
+            self.watchpoints.remove(index);
+            remove_watchpoint(context, &watchpoint);
+        }
+        "OK".to_string()
+    }
+
+    fn disconnect(&mut self, context: &mut Chip8Context, paused: &mut bool) {
+        println!("GDB disconnected");
+        self.connection = None;
+        self.input.clear();
+        self.breakpoints.clear();
+        for watchpoint in self.watchpoints.drain(..) {
+            remove_watchpoint(context, &watchpoint);
+        }
+        self.running = false;
+        context.take_watch_hit();
+        *paused = false;
+    }
+
+    fn send(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        self.write(format!("${data}#{checksum:02x}").as_bytes())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        if let Some(connection) = &mut self.connection {
+            // Replies are small, so briefly blocking is simpler than buffering partial writes.
+            connection.set_nonblocking(false)?;
+            connection.write_all(bytes)?;
+            connection.set_nonblocking(true)?;
+        }
+        Ok(())
+    }
+}
+
+fn query(packet: &str) -> String {
+    if packet.starts_with("qSupported") {
+        "PacketSize=1000;qXfer:features:read+;swbreak+".to_string()
+    } else if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+        let Some((offset, len)) = parse_range(range) else {
+            return "E01".to_string();
+        };
+        let start = offset.min(TARGET_XML.len());
+        let end = offset.saturating_add(len).min(TARGET_XML.len());
+        let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+        format!("{marker}{}", &TARGET_XML[start..end])
+    } else {
+        match packet {
+            "qAttached" => "1",
+            "qC" => "QC1",
+            "qfThreadInfo" => "m1",
+            "qsThreadInfo" => "l",
+            _ => "",
+        }
+        .to_string()
+    }
+}
+
+fn remove_watchpoint(context: &mut Chip8Context, watchpoint: &Watchpoint) {
+    if let Some(index) = context.watchpoints().iter().position(|w| w == watchpoint) {
+        context.remove_watchpoint(index);
+    }
+}
+
+fn stop_reply_for_watchpoint(watchpoint: Option<&Watchpoint>) -> String {
+    match watchpoint {
+        Some(&Watchpoint::Memory {
+            start, read, write, ..
+        }) => {
+            let kind = match (read, write) {
+                (false, true) => "watch",
+                (true, false) => "rwatch",
+                _ => "awatch",
+            };
+            format!("T05{kind}:{start:x};")
+        }
+        _ => "S05".to_string(),
+    }
+}
+
+/// SIGSEGV for a stack overflow or underflow, SIGABRT for other errors, such as a failing script.
+fn stop_reply_for_error(error: &(dyn Error + 'static)) -> String {
+    if error.is::<StackError>() {
+        "S0b".to_string()
+    } else {
+        "S06".to_string()
+    }
+}
+
+fn read_registers(context: &Chip8Context) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(REGISTERS_SIZE);
+    bytes.extend_from_slice(&context.register);
+    bytes.extend_from_slice(&context.i.to_le_bytes());
+    bytes.extend_from_slice(&(context.program_counter as u16).to_le_bytes());
+    bytes.push(context.stack.len() as u8);
+    bytes.push(context.delay_timer.time as u8);
+    bytes.push(context.sound_timer.time as u8);
+    bytes
+}
+
+fn write_registers(context: &mut Chip8Context, bytes: &[u8]) {
+    context.register.copy_from_slice(&bytes[..16]);
+    let mask = context.address_mask();
+    context.i = (u16::from_le_bytes([bytes[16], bytes[17]]) as usize & mask) as u16;
+    context.program_counter = u16::from_le_bytes([bytes[18], bytes[19]]) as usize & mask;
+    context.set_stack_depth(bytes[20] as usize);
+    context.start_delay(bytes[21] as u32);
+    context.start_sound(bytes[22] as u32);
+}
+
+/// Offset and size of GDB register `number` in the `g` packet.
+const fn register_layout(number: usize) -> Option<(usize, usize)> {
+    match number {
+        0..=15 => Some((number, 1)),
+        16 => Some((16, 2)),
+        17 => Some((18, 2)),
+        18..=20 => Some((number + 2, 1)),
+        _ => None,
+    }
+}
+
+/// Parses `register=value` of a `P` packet into the register's layout and the new value.
+fn parse_register_write(text: &str) -> Option<((usize, usize), Vec<u8>)> {
+    let (number, value) = text.split_once('=')?;
+    let layout = register_layout(usize::from_str_radix(number, 16).ok()?)?;
+    Some((layout, from_hex(value)?))
+}
+
+/// Parses `address,length`.
+fn parse_range(text: &str) -> Option<(usize, usize)> {
+    let (address, len) = text.split_once(',')?;
+    Some((
+        usize::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+/// Parses `address,length` into a range that lies within `memory_size` bytes of memory.
+fn memory_range(text: &str, memory_size: usize) -> Option<Range<usize>> {
+    let (address, len) = parse_range(text)?;
+    let end = address.checked_add(len)?;
+    (end <= memory_size).then_some(address..end)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
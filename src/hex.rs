@@ -0,0 +1,23 @@
+/// Parses a hex address such as `600` or `0x600`, which must lie within the 64 KiB of the largest
+/// memory.
+pub fn parse_address(text: &str) -> Result<usize, String> {
+    match usize::from_str_radix(digits(text), 16) {
+        Ok(address @ 0..=0xFFFF) => Ok(address),
+        Ok(_) => Err(format!("Address out of range: {text}")),
+        Err(_) => Err(format!("Invalid address: {text}")),
+    }
+}
+
+/// Parses a hex byte such as `3F` or `0x3F`.
+pub fn parse_byte(text: &str) -> Result<u8, String> {
+    u8::from_str_radix(digits(text), 16).map_err(|_| format!("Invalid byte: {text}"))
+}
+
+/// Parses a hex number of up to 16 bits such as `A2F0` or `0xA2F0`.
+pub fn parse_word(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(digits(text), 16).map_err(|_| format!("Invalid hex number: {text}"))
+}
+
+fn digits(text: &str) -> &str {
+    text.trim_start_matches("0x").trim_start_matches("0X")
+}
@@ -0,0 +1,273 @@
+/// A decoded instruction with its operands. `x` and `y` are register numbers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Instruction {
+    ClearScreen,
+    Return,
+    Jump {
+        nnn: u16,
+    },
+    Call {
+        nnn: u16,
+    },
+    SkipIfEqualByte {
+        x: u8,
+        nn: u8,
+    },
+    SkipIfNotEqualByte {
+        x: u8,
+        nn: u8,
+    },
+    SkipIfEqual {
+        x: u8,
+        y: u8,
+    },
+    SetByte {
+        x: u8,
+        nn: u8,
+    },
+    AddByte {
+        x: u8,
+        nn: u8,
+    },
+    Set {
+        x: u8,
+        y: u8,
+    },
+    Or {
+        x: u8,
+        y: u8,
+    },
+    And {
+        x: u8,
+        y: u8,
+    },
+    Xor {
+        x: u8,
+        y: u8,
+    },
+    Add {
+        x: u8,
+        y: u8,
+    },
+    Subtract {
+        x: u8,
+        y: u8,
+    },
+    ShiftRight {
+        x: u8,
+        y: u8,
+    },
+    SubtractReverse {
+        x: u8,
+        y: u8,
+    },
+    ShiftLeft {
+        x: u8,
+        y: u8,
+    },
+    SkipIfNotEqual {
+        x: u8,
+        y: u8,
+    },
+    SetIndex {
+        nnn: u16,
+    },
+    JumpWithOffset {
+        x: u8,
+        nnn: u16,
+    },
+    Random {
+        x: u8,
+        nn: u8,
+    },
+    Draw {
+        x: u8,
+        y: u8,
+        n: u8,
+    },
+    SkipIfPressed {
+        x: u8,
+    },
+    SkipIfNotPressed {
+        x: u8,
+    },
+    ReadDelay {
+        x: u8,
+    },
+    StartDelay {
+        x: u8,
+    },
+    StartSound {
+        x: u8,
+    },
+    AddToIndex {
+        x: u8,
+    },
+    GetKey {
+        x: u8,
+    },
+    FontChar {
+        x: u8,
+    },
+    BinaryCodedDecimal {
+        x: u8,
+    },
+    StoreRegisters {
+        x: u8,
+    },
+    LoadRegisters {
+        x: u8,
+    },
+    /// `0NNN` machine code routines and unassigned opcodes, which do nothing.
+    Ignored,
+}
+
+impl Instruction {
+    pub const fn decode(opcode: u16) -> Self {
+        let x = ((opcode >> 8) & 0xF) as u8;
+        let y = ((opcode >> 4) & 0xF) as u8;
+        let nnn = opcode & 0xFFF;
+        let nn = (opcode & 0xFF) as u8;
+        let n = (opcode & 0xF) as u8;
+
+        match opcode >> 12 {
+            0x0 => match nnn {
+                0x0E0 => Instruction::ClearScreen,
+                0x0EE => Instruction::Return,
+                _ => Instruction::Ignored,
+            },
+            0x1 => Instruction::Jump { nnn },
+            0x2 => Instruction::Call { nnn },
+            0x3 => Instruction::SkipIfEqualByte { x, nn },
+            0x4 => Instruction::SkipIfNotEqualByte { x, nn },
+            0x5 => Instruction::SkipIfEqual { x, y },
+            0x6 => Instruction::SetByte { x, nn },
+            0x7 => Instruction::AddByte { x, nn },
+            0x8 => match n {
+                0x0 => Instruction::Set { x, y },
+                0x1 => Instruction::Or { x, y },
+                0x2 => Instruction::And { x, y },
+                0x3 => Instruction::Xor { x, y },
+                0x4 => Instruction::Add { x, y },
+                0x5 => Instruction::Subtract { x, y },
+                0x6 => Instruction::ShiftRight { x, y },
+                0x7 => Instruction::SubtractReverse { x, y },
+                0xE => Instruction::ShiftLeft { x, y },
+                _ => Instruction::Ignored,
+            },
+            0x9 => Instruction::SkipIfNotEqual { x, y },
+            0xA => Instruction::SetIndex { nnn },
+            0xB => Instruction::JumpWithOffset { x, nnn },
+            0xC => Instruction::Random { x, nn },
+            0xD => Instruction::Draw { x, y, n },
+            0xE => match nn {
+                0x9E => Instruction::SkipIfPressed { x },
+                0xA1 => Instruction::SkipIfNotPressed { x },
+                _ => Instruction::Ignored,
+            },
+            _ => match nn {
+                0x07 => Instruction::ReadDelay { x },
+                0x0A => Instruction::GetKey { x },
+                0x15 => Instruction::StartDelay { x },
+                0x18 => Instruction::StartSound { x },
+                0x1E => Instruction::AddToIndex { x },
+                0x29 => Instruction::FontChar { x },
+                0x33 => Instruction::BinaryCodedDecimal { x },
+                0x55 => Instruction::StoreRegisters { x },
+                0x65 => Instruction::LoadRegisters { x },
+                _ => Instruction::Ignored,
+            },
+        }
+    }
+}
+
+/// The big endian opcode at `address`, wrapping around at the end of `memory`.
+pub(crate) fn read_opcode(memory: &[u8], address: usize) -> u16 {
+    u16::from_be_bytes([memory[address], memory[(address + 1) % memory.len()]])
+}
+
+/// Decoded instructions by address, so loops are decoded only once. Entries are invalidated when
+/// the memory they were decoded from is written, which keeps self-modifying code correct.
+pub(crate) struct InstructionCache {
+    entries: Box<[Option<(u16, Instruction)>]>,
+}
+
+impl InstructionCache {
+    /// An empty cache for `memory_size` bytes of memory.
+    pub(crate) fn new(memory_size: usize) -> Self {
+        InstructionCache {
+            entries: vec![None; memory_size].into_boxed_slice(),
+        }
+    }
+
+    /// The opcode and instruction at `address`, decoded from `memory` on first use.
+    pub(crate) fn get(&mut self, address: usize, memory: &[u8]) -> (u16, Instruction) {
+        *self.entries[address].get_or_insert_with(|| {
+            let opcode = read_opcode(memory, address);
+            (opcode, Instruction::decode(opcode))
+        })
+    }
+
+    /// Forgets the instructions overlapping the `len` bytes at `start`, including the one
+    /// starting the byte before, which wraps around to the end of memory.
+    pub(crate) fn invalidate(&mut self, start: usize, len: usize) {
+        let size = self.entries.len();
+        let before = (start + size - 1) % size;
+        for offset in 0..=len {
+            self.entries[(before + offset) % size] = None;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip8Context, Quirks};
+
+    #[test]
+    fn the_cache_runs_instructions_overwritten_by_the_program() {
+        let program = vec![
+            0xA2, 0x0C, // LD I, 0x20C
+            0x22, 0x0C, // CALL 0x20C, which decodes LD V2, 0x01
+            0x60, 0x62, // LD V0, 0x62
+            0x61, 0x77, // LD V1, 0x77
+            0xF1, 0x55, // LD [I], V1, which turns 0x20C into LD V2, 0x77
+            0x22, 0x0C, // CALL 0x20C
+            0x62, 0x01, // LD V2, 0x01
+            0x00, 0xEE, // RET
+        ];
+        let quirks = Quirks {
+            store_load: false,
+            ..Quirks::default()
+        };
+        let mut context = Chip8Context::builder(program)
+            .quirks(quirks)
+            .build()
+            .unwrap();
+        context.enable_instruction_cache();
+        for _ in 0..4 {
+            context.step().unwrap();
+        }
+        assert_eq!(context.registers()[2], 0x01);
+        for _ in 0..6 {
+            context.step().unwrap();
+        }
+        assert_eq!(context.registers()[2], 0x77);
+        assert_eq!(context.program_counter(), 0x20C);
+    }
+
+    #[test]
+    fn invalidating_address_zero_forgets_the_instruction_at_the_end_of_memory() {
+        let mut memory = vec![0; 0x1000];
+        memory[0xFFF] = 0x12;
+        let mut cache = InstructionCache::new(memory.len());
+        assert_eq!(cache.get(0xFFF, &memory).0, 0x1200);
+        memory[0] = 0x34;
+        cache.invalidate(0, 1);
+        assert_eq!(cache.get(0xFFF, &memory).0, 0x1234);
+    }
+}
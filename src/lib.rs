@@ -1,534 +1,905 @@
+//! A CHIP-8 interpreter. Create a [`Chip8Context`] with [`Chip8Context::builder`], run it with
+//! [`Chip8Context::step`] or [`Chip8Context::run_frame`], press keys with
+//! [`Chip8Context::set_key`] and inspect the machine through its read-only accessors. Windows,
+//! terminals and other frontends plug in through the traits in [`frontend`].
+
+use builder::Chip8Builder;
+use cheats::Cheat;
+use coverage::Coverage;
+use framebuffer::Framebuffer;
+use frontend::{AudioSink, InputSource, VideoSink};
+use instruction::{Instruction, InstructionCache, read_opcode};
+use platform::Platform;
+use profiler::Profiler;
 use rand::{Rng, rngs::ThreadRng};
-use sdl2::{
-    AudioSubsystem,
-    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
-    keyboard::{KeyboardState, Scancode},
-    pixels::Color,
-    rect::Point,
-    render::WindowCanvas,
-    video::Window,
-};
-use std::error::Error;
+use recording::Recorder;
+use rom::RomError;
+use script::Script;
+use stack::StackError;
+use std::{error::Error, path::Path};
+use trace::Tracer;
+use watch::{Access, Register, WatchHit, Watchpoint};
+
+pub mod builder;
+pub mod cheat_search;
+pub mod cheats;
+pub mod coverage;
+pub mod debugger;
+pub mod disassembler;
+pub mod framebuffer;
+pub mod frontend;
+pub mod gdb;
+pub mod hex;
+pub mod instruction;
+#[cfg(feature = "sdl")]
+pub mod memory_viewer;
+pub mod pacing;
+pub mod platform;
+pub mod profiler;
+pub mod recording;
+pub mod rom;
+pub mod save_state;
+pub mod screenshot;
+pub mod script;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod stack;
+pub mod terminal;
+pub mod trace;
+pub mod watch;
 
 pub const WINDOW_SIZE: (u32, u32) = (1024, 512);
 pub const LOGICAL_WINDOW_SIZE: (u32, u32) = (64, 32);
+pub const SCALE: u32 = WINDOW_SIZE.0 / LOGICAL_WINDOW_SIZE.0;
 pub const TARGET_IPS: u32 = 700;
+pub const PALETTE: Palette = Palette {
+    foreground: Color::WHITE,
+    background: Color::BLACK,
+};
+
+/// Behaviours that differ between CHIP-8 interpreters. The defaults follow the `alt_*` features.
+#[derive(Clone, Copy)]
+pub struct Quirks {
+    /// `8XY6`/`8XYE` copy VY into VX before shifting.
+    pub shift: bool,
+    /// `BXNN` adds VX instead of V0 to the jump target.
+    pub jump: bool,
+    /// `FX55`/`FX65` increment the index register.
+    pub store_load: bool,
+}
+
+// Not derived, as the defaults depend on the enabled features.
+#[allow(clippy::derivable_impls)]
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift: cfg!(feature = "alt_shift"),
+            jump: cfg!(feature = "alt_jump"),
+            store_load: cfg!(feature = "alt_store_load"),
+        }
+    }
+}
+
+/// An RGB color, independent of any frontend library.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(0xFF, 0xFF, 0xFF);
+    pub const BLACK: Color = Color::rgb(0x00, 0x00, 0x00);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+}
+
+/// Colors used for lit and unlit pixels.
+#[derive(Clone, Copy)]
+pub struct Palette {
+    pub foreground: Color,
+    pub background: Color,
+}
 
 pub struct Chip8Context {
-    renderer: Renderer,
-    memory: [u8; 4096],
-    display: [[bool; 64]; 32],
+    video: Option<Box<dyn VideoSink>>,
+    audio: Option<Box<dyn AudioSink>>,
+    display_changed: bool,
+    memory: Box<[u8]>,
+    platform: Platform,
+    display: Framebuffer,
     program_counter: usize,
     i: u16,
     stack: Vec<usize>,
-    delay_timer: DTimer,
-    sound_timer: STimer,
+    delay_timer: Timer,
+    sound_timer: Timer,
     register: [u8; 16],
     random_device: ThreadRng,
     keypad: [bool; 16],
+    // The key pressed during `FX0A`, which waits for its release.
+    key_wait: Option<u8>,
+    cycles: u64,
+    // Instructions executed in the current frame, and the remainder of `ips / 60` carried over
+    // from earlier frames so that `ips` instructions run every second on average.
+    frame_instructions: u32,
+    ips_remainder: u32,
+    recorder: Option<Recorder>,
+    quirks: Quirks,
+    ips: u32,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    rom: Vec<u8>,
+    tracer: Option<Tracer>,
+    watchpoints: Vec<Watchpoint>,
+    pending_watch: Option<(usize, String)>,
+    watch_hit: Option<WatchHit>,
+    script: Option<Box<Script>>,
+    last_write: Option<(usize, usize)>,
+    cheats: Vec<Cheat>,
+    instruction_cache: Option<InstructionCache>,
 }
 
 impl Chip8Context {
-    pub fn new(
-        renderer: Renderer,
-        audio: &AudioSubsystem,
-        game_file: Vec<u8>,
-    ) -> Result<Self, Box<dyn Error>> {
+    /// Starts configuring a context that runs `rom`.
+    pub fn builder(rom: Vec<u8>) -> Chip8Builder {
+        Chip8Builder::new(rom)
+    }
+    /// Creates a context without a window or audio device, see [`Chip8Builder::build`].
+    pub(crate) fn with_platform(game_file: Vec<u8>, platform: Platform) -> Result<Self, RomError> {
+        platform.check_program(&game_file)?;
         Ok(Chip8Context {
-            renderer,
-            memory: init_memory(game_file),
-            display: [[false; 64]; 32],
-            program_counter: INSTR_OFFSET,
+            video: None,
+            audio: None,
+            display_changed: true,
+            memory: init_memory(&game_file, &platform),
+            platform,
+            rom: game_file,
+            display: Framebuffer::default(),
+            program_counter: platform.entry_point,
             i: 0,
             stack: Vec::with_capacity(16),
-            delay_timer: DTimer::new(),
-            sound_timer: STimer::new(audio)?,
+            delay_timer: Timer::new(),
+            sound_timer: Timer::new(),
             register: [0; 16],
             random_device: rand::thread_rng(),
             keypad: [false; 16],
+            key_wait: None,
+            cycles: 0,
+            frame_instructions: 0,
+            ips_remainder: 0,
+            recorder: None,
+            quirks: Quirks::default(),
+            ips: TARGET_IPS,
+            profiler: None,
+            coverage: None,
+            tracer: None,
+            watchpoints: Vec::new(),
+            pending_watch: None,
+            watch_hit: None,
+            script: None,
+            last_write: None,
+            cheats: Vec::new(),
+            instruction_cache: None,
         })
     }
     const fn start_delay(&mut self, duration: u32) {
-        self.delay_timer.time = duration * (TARGET_IPS / 60);
+        self.delay_timer.time = duration;
     }
     const fn start_sound(&mut self, duration: u32) {
-        self.sound_timer.time = duration * (TARGET_IPS / 60);
+        self.sound_timer.time = duration;
     }
-    fn process_instructions(&mut self) {
-        let instr1 = self.memory[self.program_counter];
-        let instr2 = self.memory[self.program_counter + 1];
-        let instr = ((instr1 as u16) << 8) + instr2 as u16;
+    fn process_instructions(&mut self) -> Result<(), StackError> {
+        let address = self.program_counter;
+        let (instr, instruction) = match &mut self.instruction_cache {
+            Some(cache) => cache.get(address, &self.memory),
+            None => {
+                let instr = read_opcode(&self.memory, address);
+                (instr, Instruction::decode(instr))
+            }
+        };
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(self.program_counter, instr, &self.memory);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_instruction(self.program_counter);
+        }
+        self.last_write = None;
+        self.access_memory(address, 2, Access::Fetch);
+        let registers_before = (!self.watchpoints.is_empty()).then_some((self.register, self.i));
         self.program_counter += 2;
-        self.decode_instruction(instr);
-        if self.program_counter >= 4096 {
-            self.program_counter = INSTR_OFFSET;
+        if let Err(error) = self.execute(instruction) {
+            self.program_counter = address;
+            return Err(error);
+        }
+        if let Some((start, len)) = self.last_write {
+            self.load_mapped_display(start, len);
+            self.load_memory_stack(start, len);
+        }
+        if let Some((register, i)) = registers_before {
+            self.check_register_watches(register, i);
         }
+        if let Some((watchpoint, detail)) = self.pending_watch.take() {
+            self.watch_hit = Some(WatchHit {
+                watchpoint,
+                address,
+                opcode: instr,
+                detail,
+            });
+        }
+        if self.program_counter >= self.memory.len() {
+            self.program_counter = self.platform.entry_point;
+        }
+        Ok(())
     }
-    fn decode_instruction(&mut self, opcode: u16) {
-        let vx: usize = bit_i(opcode, 1) as usize;
-        let vy: usize = bit_i(opcode, 2) as usize;
-        let nnn: u16 = opcode & 0xFFF;
-        let nn: u8 = (opcode & 0xFF) as u8;
-        let n: u8 = (opcode & 0xF) as u8;
-
-        match bit_i(opcode, 0) {
-            0x0 => {
-                match nnn {
-                    // CLEAR SCREEN
-                    0x0E0 => self.clear_screen(true),
-                    // RETURN FROM SUBROUTINE
-                    0x0EE => {
-                        self.program_counter = self.stack.pop().unwrap();
-                    }
-                    _ => (),
-                }
+    fn execute(&mut self, instruction: Instruction) -> Result<(), StackError> {
+        match instruction {
+            // CLEAR SCREEN
+            Instruction::ClearScreen => self.clear_screen(),
+            // RETURN FROM SUBROUTINE
+            Instruction::Return => {
+                self.program_counter = self.pop_return_address(self.program_counter - 2)?;
             }
-            0x1 => {
+            Instruction::Jump { nnn } => {
                 // JUMP
                 let mem_location = nnn;
                 self.program_counter = mem_location as usize;
             }
-            0x2 => {
+            Instruction::Call { nnn } => {
                 // SUBROUTINE
                 let mem_location = nnn;
-                self.stack.push(self.program_counter);
+                self.push_return_address(self.program_counter - 2)?;
                 self.program_counter = mem_location as usize;
             }
-            0x3 => {
+            Instruction::SkipIfEqualByte { x, nn } => {
                 // SKIP IF VX == NN
-                if self.register[vx] == nn {
+                if self.register[x as usize] == nn {
                     self.program_counter += 2;
                 }
             }
-            0x4 => {
+            Instruction::SkipIfNotEqualByte { x, nn } => {
                 // SKIP IF VX != NN
-                if self.register[vx] != nn {
+                if self.register[x as usize] != nn {
                     self.program_counter += 2;
                 }
             }
-            0x5 => {
+            Instruction::SkipIfEqual { x, y } => {
                 // SKIP IF VX == VY
-                if self.register[vx] == self.register[vy] {
+                if self.register[x as usize] == self.register[y as usize] {
                     self.program_counter += 2;
                 }
             }
-            0x6 => {
+            Instruction::SetByte { x, nn } => {
                 // SET REGISTER
-                self.register[vx] = nn;
+                self.register[x as usize] = nn;
             }
-            0x7 => {
+            Instruction::AddByte { x, nn } => {
                 // ADD TO REGISTER
+                let vx = x as usize;
                 self.register[vx] = ((self.register[vx] as u16 + nn as u16) % (0xFF + 1)) as u8;
             }
-            0x8 => {
-                match n {
-                    0x0 => {
-                        // SET
-                        self.register[vx] = self.register[vy];
-                    }
-                    0x1 => {
-                        // BINARY OR
-                        self.register[vx] |= self.register[vy];
-                    }
-                    0x2 => {
-                        // BINARY AND
-                        self.register[vx] &= self.register[vy];
-                    }
-                    0x3 => {
-                        // LOGICAL XOR
-                        self.register[vx] ^= self.register[vy];
-                    }
-                    0x4 => {
-                        // ADD
-                        if self.register[vx].checked_add(self.register[vy]).is_none() {
-                            self.register[0xF] = 1;
-                            self.register[vx] = ((self.register[vx] as u16
-                                + self.register[vy] as u16)
-                                % (0xFF + 1)) as u8;
-                        } else {
-                            self.register[0xF] = 0;
-                            self.register[vx] += self.register[vy];
-                        }
-                    }
-                    0x5 => {
-                        // SUBTRACT Y FROM X
-                        if self.register[vx] > self.register[vy] {
-                            self.register[0xF] = 1;
-                        } else {
-                            self.register[0xF] = 0;
-                        }
-                        self.register[vx] -= self.register[vy];
-                    }
-                    0x7 => {
-                        // SUBTRACT X FROM Y
-                        if self.register[vy] > self.register[vx] {
-                            self.register[0xF] = 1;
-                        } else {
-                            self.register[0xF] = 0;
-                        }
-                        self.register[vx] = self.register[vy] - self.register[vx];
-                    }
-                    0x6 => {
-                        // SHIFT RIGHT
-                        #[cfg(feature = "alt_shift")]
-                        {
-                            self.register[vx] = self.register[vy];
-                        }
-
-                        let entry = self.register[vx];
-                        self.register[vx] >>= 1;
+            Instruction::Set { x, y } => {
+                // SET
+                self.register[x as usize] = self.register[y as usize];
+            }
+            Instruction::Or { x, y } => {
+                // BINARY OR
+                self.register[x as usize] |= self.register[y as usize];
+            }
+            Instruction::And { x, y } => {
+                // BINARY AND
+                self.register[x as usize] &= self.register[y as usize];
+            }
+            Instruction::Xor { x, y } => {
+                // LOGICAL XOR
+                self.register[x as usize] ^= self.register[y as usize];
+            }
+            Instruction::Add { x, y } => {
+                // ADD
+                let (vx, vy) = (x as usize, y as usize);
+                if self.register[vx].checked_add(self.register[vy]).is_none() {
+                    self.register[0xF] = 1;
+                    self.register[vx] =
+                        ((self.register[vx] as u16 + self.register[vy] as u16) % (0xFF + 1)) as u8;
+                } else {
+                    self.register[0xF] = 0;
+                    self.register[vx] += self.register[vy];
+                }
+            }
+            Instruction::Subtract { x, y } => {
+                // SUBTRACT Y FROM X
+                let (vx, vy) = (x as usize, y as usize);
+                if self.register[vx] > self.register[vy] {
+                    self.register[0xF] = 1;
+                } else {
+                    self.register[0xF] = 0;
+                }
+                self.register[vx] -= self.register[vy];
+            }
+            Instruction::SubtractReverse { x, y } => {
+                // SUBTRACT X FROM Y
+                let (vx, vy) = (x as usize, y as usize);
+                if self.register[vy] > self.register[vx] {
+                    self.register[0xF] = 1;
+                } else {
+                    self.register[0xF] = 0;
+                }
+                self.register[vx] = self.register[vy] - self.register[vx];
+            }
+            Instruction::ShiftRight { x, y } => {
+                // SHIFT RIGHT
+                let (vx, vy) = (x as usize, y as usize);
+                if self.quirks.shift {
+                    self.register[vx] = self.register[vy];
+                }
 
-                        let shifted_off: u8 = entry & 0x1;
-                        self.register[0xF] = shifted_off;
-                    }
-                    0xE => {
-                        // SHIFT LEFT
-                        #[cfg(feature = "alt_shift")]
-                        {
-                            self.register[vx] = self.register[vy];
-                        }
-                        let entry = self.register[vx];
-                        self.register[vx] <<= 1;
+                let entry = self.register[vx];
+                self.register[vx] >>= 1;
 
-                        let shifted_off: u8 = (entry & 0x80) >> 7;
-                        self.register[0xF] = shifted_off;
-                    }
-                    _ => (),
+                let shifted_off: u8 = entry & 0x1;
+                self.register[0xF] = shifted_off;
+            }
+            Instruction::ShiftLeft { x, y } => {
+                // SHIFT LEFT
+                let (vx, vy) = (x as usize, y as usize);
+                if self.quirks.shift {
+                    self.register[vx] = self.register[vy];
                 }
+                let entry = self.register[vx];
+                self.register[vx] <<= 1;
+
+                let shifted_off: u8 = (entry & 0x80) >> 7;
+                self.register[0xF] = shifted_off;
             }
-            0x9 => {
+            Instruction::SkipIfNotEqual { x, y } => {
                 // SKIP IF VX != VY
-                if self.register[vx] != self.register[vy] {
+                if self.register[x as usize] != self.register[y as usize] {
                     self.program_counter += 2;
                 }
             }
-            0xA => {
+            Instruction::SetIndex { nnn } => {
                 // SET INDEX REGISTER
                 self.i = nnn;
             }
-            0xB => {
+            Instruction::JumpWithOffset { x, nnn } => {
                 // JUMP WITH OFFSET
                 let mem_location = nnn;
-                #[cfg(feature = "alt_jump")]
-                {
-                    let offset_regx = self.register[vx];
+                if self.quirks.jump {
+                    let offset_regx = self.register[x as usize];
                     self.program_counter = (mem_location + offset_regx as u16) as usize;
-                }
-                #[cfg(not(feature = "alt_jump"))]
-                {
+                } else {
                     let offset_reg0 = self.register[0];
                     self.program_counter = (mem_location + offset_reg0 as u16) as usize;
                 }
             }
-            0xC => {
+            Instruction::Random { x, nn } => {
                 // RANDOM
                 let random_number: u8 = self.random_device.gen_range(0x0..0xFF);
                 let final_value = random_number & nn;
-                self.register[vx] = final_value;
+                self.register[x as usize] = final_value;
             }
-            0xD => {
+            Instruction::Draw { x, y, n } => {
                 // DISPLAY/DRAW
-                let x = self.register[vx] % 64;
-                let mut curr_y = self.register[vy] % 32;
+                let (width, height) = (self.display.width(), self.display.height());
+                let x = self.register[x as usize] as usize % width;
+                let y = self.register[y as usize] as usize % height;
 
                 self.register[0xF] = 0;
-                for byte in self.get_mem_region(self.i as usize, (self.i + (opcode & 0xF)) as usize)
-                {
-                    if curr_y >= 32 {
-                        return;
-                    }
-                    let mut curr_x = x;
-                    for bit in 0..8 {
-                        if x + bit >= 64 {
-                            break;
-                        }
-
-                        let new_pixel = (byte >> (8 - bit - 1)) & 0b1;
-                        let old_pixel = self.read_pixel_at(curr_x, curr_y) as u8;
-
-                        if new_pixel == 1 && old_pixel == 0 {
-                            self.draw_pixel_at(curr_x, curr_y).unwrap();
-                        } else if new_pixel == 1 && old_pixel == 1 {
-                            self.register[0xF] = 1;
-                            self.remove_pixel_at(curr_x, curr_y).unwrap();
-                        }
-
-                        curr_x += 1;
+                self.access_memory(self.i as usize, n as usize, Access::Read);
+                for (row, curr_y) in (y..height).take(n as usize).enumerate() {
+                    let byte = self.memory[(self.i as usize + row) & self.address_mask()];
+                    if self.display.draw_sprite_row(x, curr_y, byte) {
+                        self.register[0xF] = 1;
                     }
-                    curr_y += 1;
                 }
+                self.redraw_mapped();
             }
-            0xE => match nn {
-                0x9E => {
-                    // SKIP IF PRESSED
-                    if self.keypad[self.register[vx] as usize] {
-                        self.program_counter += 2;
-                    }
-                }
-                0xA1 => {
-                    // SKIP IF NOT PRESSED
-                    if !self.keypad[self.register[vx] as usize] {
-                        self.program_counter += 2;
-                    }
-                }
-                _ => (),
-            },
-            0xF => match nn {
-                0x07 => {
-                    // READ DELAY
-                    self.register[vx] = self.delay_timer.time as u8;
+            Instruction::SkipIfPressed { x } => {
+                // SKIP IF PRESSED
+                if self.keypad[self.register[x as usize] as usize] {
+                    self.program_counter += 2;
                 }
-                0x15 => {
-                    // START DELAY
-                    self.start_delay(self.register[vx] as u32);
+            }
+            Instruction::SkipIfNotPressed { x } => {
+                // SKIP IF NOT PRESSED
+                if !self.keypad[self.register[x as usize] as usize] {
+                    self.program_counter += 2;
                 }
-                0x18 => {
-                    // START SOUND
-                    self.start_sound(self.register[vx] as u32);
+            }
+            Instruction::ReadDelay { x } => {
+                // READ DELAY
+                self.register[x as usize] = self.delay_timer.time as u8;
+            }
+            Instruction::StartDelay { x } => {
+                // START DELAY
+                self.start_delay(self.register[x as usize] as u32);
+            }
+            Instruction::StartSound { x } => {
+                // START SOUND
+                self.start_sound(self.register[x as usize] as u32);
+            }
+            Instruction::AddToIndex { x } => {
+                // ADD TO INDEX
+                let i = self.i as usize + self.register[x as usize] as usize;
+                if i > self.address_mask() {
+                    self.register[0xF] = 1;
                 }
-                0x1E => {
-                    // ADD TO INDEX
-                    self.i += self.register[vx] as u16;
-                    if self.i > 0xFFF {
-                        self.register[0xF] = 1;
+                self.i = (i & self.address_mask()) as u16;
+            }
+            Instruction::GetKey { x } => {
+                // GET KEY, which completes once a pressed key is released again
+                match self.key_wait {
+                    Some(key) if !self.keypad[key as usize] => {
+                        self.register[x as usize] = key;
+                        self.key_wait = None;
                     }
-                }
-                0x0A => {
-                    // GET KEY
-                    if self.keypad.iter().all(|b| !b) {
+                    Some(_) => self.program_counter -= 2,
+                    None => {
+                        self.key_wait = self.keypad.iter().position(|&b| b).map(|k| k as u8);
                         self.program_counter -= 2;
+                    }
+                }
+            }
+            Instruction::FontChar { x } => {
+                // FONT CHAR
+                self.i = self.platform.font_address as u16 + 5 * self.register[x as usize] as u16;
+            }
+            Instruction::BinaryCodedDecimal { x } => {
+                // BINARY CODED DECIMAL CONVERSION
+                let val: u8 = self.register[x as usize];
+                let d1: u8 = ((val / 10) / 10) % 10;
+                let d2: u8 = (val / 10) % 10;
+                let d3: u8 = val % 10;
+                self.access_memory(self.i as usize, 3, Access::Write);
+                self.memory[self.i as usize & self.address_mask()] = d1;
+                self.memory[(self.i as usize + 1) & self.address_mask()] = d2;
+                self.memory[(self.i as usize + 2) & self.address_mask()] = d3;
+            }
+            Instruction::StoreRegisters { x } => {
+                // STORE REGISTERS IN MEMORY
+                let vx = x as usize;
+                self.access_memory(
+                    self.i as usize + self.quirks.store_load as usize,
+                    vx + 1,
+                    Access::Write,
+                );
+                for i in 0..=vx {
+                    let val = self.register[i];
+                    if self.quirks.store_load {
+                        self.i = ((self.i as usize + 1) & self.address_mask()) as u16;
+                        self.memory[self.i as usize & self.address_mask()] = val;
                     } else {
-                        for k_i in 0..self.keypad.len() {
-                            if self.keypad[k_i] {
-                                self.register[vx] = k_i as u8;
-                                break;
-                            }
-                        }
+                        self.memory[(self.i as usize + i) & self.address_mask()] = val;
                     }
                 }
-                0x29 => {
-                    // FONT CHAR
-                    self.i = FONT_OFFSET as u16 + 5 * self.register[vx] as u16;
+            }
+            Instruction::LoadRegisters { x } => {
+                // STORE MEMORY IN REGISTERS
+                let vx = x as usize;
+                self.access_memory(
+                    self.i as usize + self.quirks.store_load as usize,
+                    vx + 1,
+                    Access::Read,
+                );
+                for i in 0..=vx {
+                    if self.quirks.store_load {
+                        self.i = ((self.i as usize + 1) & self.address_mask()) as u16;
+                        self.register[i] = self.memory[self.i as usize & self.address_mask()];
+                    } else {
+                        self.register[i] = self.memory[(self.i as usize + i) & self.address_mask()];
+                    }
                 }
-                0x33 => {
-                    // BINARY CODED DECIMAL CONVERSION
-                    let val: u8 = self.register[vx];
-                    let d1: u8 = ((val / 10) / 10) % 10;
-                    let d2: u8 = (val / 10) % 10;
-                    let d3: u8 = val % 10;
-                    self.memory[self.i as usize] = d1;
-                    self.memory[self.i as usize + 1] = d2;
-                    self.memory[self.i as usize + 2] = d3;
+            }
+            Instruction::Ignored => (),
+        }
+        Ok(())
+    }
+    fn access_memory(&mut self, start: usize, len: usize, access: Access) {
+        if access != Access::Fetch
+            && let Some(coverage) = &mut self.coverage
+        {
+            coverage.record_data(start, len);
+        }
+        if access == Access::Write {
+            self.last_write = Some((start, len));
+            if let Some(cache) = &mut self.instruction_cache {
+                cache.invalidate(start, len);
+            }
+        }
+        if self.pending_watch.is_some() {
+            return;
+        }
+        for (index, watchpoint) in self.watchpoints.iter().enumerate() {
+            if let Some(address) = watchpoint.matched_address(start, len, access) {
+                self.pending_watch = Some((index, format!("{access} {address:#05X}")));
+                return;
+            }
+        }
+    }
+    fn check_register_watches(&mut self, register: [u8; 16], i: u16) {
+        if self.pending_watch.is_some() {
+            return;
+        }
+        for (index, watchpoint) in self.watchpoints.iter().enumerate() {
+            let detail = match *watchpoint {
+                Watchpoint::Register(Register::V(x))
+                    if register[x as usize] != self.register[x as usize] =>
+                {
+                    format!(
+                        "V{x:X} {:02X} -> {:02X}",
+                        register[x as usize], self.register[x as usize]
+                    )
                 }
-                0x55 => {
-                    // STORE REGISTERS IN MEMORY
-                    for i in 0..=vx {
-                        let val = self.register[i];
-                        #[cfg(feature = "alt_store_load")]
-                        {
-                            self.i += 1;
-                            self.memory[self.i as usize] = val;
-                        }
-
-                        #[cfg(not(feature = "alt_store_load"))]
-                        {
-                            self.memory[(self.i + i as u16) as usize] = val;
-                        }
-                    }
+                Watchpoint::Register(Register::I) if i != self.i => {
+                    format!(
+                        "I {i:0digits$X} -> {:0digits$X}",
+                        self.i,
+                        digits = self.address_digits()
+                    )
                 }
-                0x65 => {
-                    // STORE MEMORY IN REGISTERS
-                    for i in 0..=vx {
-                        #[cfg(feature = "alt_store_load")]
-                        {
-                            self.i += 1;
-                            self.register[i] = self.memory[self.i as usize];
-                        }
-                        #[cfg(not(feature = "alt_store_load"))]
-                        {
-                            self.register[i] = self.memory[(self.i + i as u16) as usize];
-                        }
-                    }
+                _ => continue,
+            };
+            self.pending_watch = Some((index, detail));
+            return;
+        }
+    }
+    fn clear_screen(&mut self) {
+        self.display.clear();
+        self.redraw_mapped();
+    }
+    /// Marks the display as changed for the next [`Chip8Context::present`] and copies it into
+    /// memory if it is mapped there. Returns the start and length of the memory that changed.
+    fn redraw(&mut self) -> Option<(usize, usize)> {
+        self.display_changed = true;
+        let address = self.platform.display_address?;
+        let row_bytes = self.display.width() / 8;
+        let mut changed: Option<(usize, usize)> = None;
+        for y in 0..self.display.height() {
+            let pixels = self.display.row(y).to_be_bytes();
+            for (offset, &byte) in pixels[..row_bytes].iter().enumerate() {
+                let offset = y * row_bytes + offset;
+                let target = (address + offset) & self.address_mask();
+                if self.memory[target] != byte {
+                    self.memory[target] = byte;
+                    let first = changed.map_or(offset, |(first, _)| first);
+                    changed = Some((first, offset));
                 }
-                _ => (),
-            },
-            _ => (),
+            }
         }
+        changed.map(|(first, last)| ((address + first) & self.address_mask(), last - first + 1))
     }
-    fn get_mem_region(&self, start: usize, end: usize) -> Vec<u8> {
-        self.memory[start..end].to_vec()
+    /// Redraws after `DXYN` and `00E0`, which report the display memory they changed as a write,
+    /// so that watchpoints, script hooks and coverage see it.
+    fn redraw_mapped(&mut self) {
+        if let Some((start, len)) = self.redraw() {
+            self.access_memory(start, len, Access::Write);
+        }
     }
-    fn clear_screen(&mut self, present: bool) {
-        self.renderer.canvas.set_draw_color(Color::BLACK);
-        self.renderer.canvas.clear();
-        if present {
-            self.display = [[false; 64]; 32];
-            self.renderer.to_be_rendered = vec![];
-            self.renderer.canvas.present();
+    /// Updates the display after the `len` bytes of memory at `start` were written, if the
+    /// display is mapped there.
+    fn load_mapped_display(&mut self, start: usize, len: usize) {
+        let Some(address) = self.platform.display_address else {
+            return;
+        };
+        let row_bytes = self.display.width() / 8;
+        let end = address + row_bytes * self.display.height();
+        if start + len <= address || start >= end {
+            return;
+        }
+        for y in 0..self.display.height() {
+            let mut pixels = [0; 16];
+            for (offset, byte) in pixels[..row_bytes].iter_mut().enumerate() {
+                *byte = self.memory[(address + y * row_bytes + offset) & self.address_mask()];
+            }
+            self.display.set_row(y, u128::from_be_bytes(pixels));
         }
+        self.display_changed = true;
     }
-    fn draw_pixel_at(&mut self, x: u8, y: u8) -> Result<(), Box<dyn Error>> {
-        self.renderer
-            .to_be_rendered
-            .push(Point::new(x as i32, y as i32));
-        self.renderer.draw()?;
-        self.display[y as usize][x as usize] = true;
+    /// Passes the display to the video sink, telling it whether it changed since the last call.
+    pub fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(video) = &mut self.video {
+            video.present(&self.display, self.display_changed)?;
+        }
+        self.display_changed = false;
         Ok(())
     }
-    fn remove_pixel_at(&mut self, x: u8, y: u8) -> Result<(), Box<dyn Error>> {
-        for (i, pixel) in self.renderer.to_be_rendered.iter().enumerate() {
-            if pixel.x == x as i32 && pixel.y == y as i32 {
-                self.renderer.to_be_rendered.remove(i);
-                self.display[y as usize][x as usize] = false;
-                self.clear_screen(false);
-                self.renderer.draw()?;
-                return Ok(());
+    /// Executes one instruction, ending the 60 Hz frame after
+    /// [`Chip8Context::instructions_per_frame`] instructions.
+    pub fn step(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.tracer.is_some() {
+            let line = self.trace_line();
+            if let Some(tracer) = &mut self.tracer {
+                tracer.write_line(&line)?;
             }
         }
+        self.run_script(Script::before_instruction)?;
+        self.process_instructions()?;
+        self.run_script(Script::after_instruction)?;
+
+        self.cycles += 1;
+        self.frame_instructions += 1;
+        if self.frame_instructions >= self.instructions_per_frame() {
+            self.ips_remainder = (self.ips_due() % u64::from(pacing::FRAME_RATE)) as u32;
+            self.frame_instructions = 0;
+            self.end_frame()?;
+        }
+
         Ok(())
     }
-    const fn read_pixel_at(&self, x: u8, y: u8) -> bool {
-        self.display[y as usize][x as usize]
+    /// Executes instructions until the current 60 Hz frame ends.
+    pub fn run_frame(&mut self) -> Result<(), Box<dyn Error>> {
+        loop {
+            self.step()?;
+            if self.frame_instructions == 0 {
+                return Ok(());
+            }
+        }
+    }
+    /// Restarts the ROM as if it was just loaded. Settings, sinks, cheats, watchpoints and scripts
+    /// are kept.
+    pub fn reset(&mut self) {
+        self.memory = init_memory(&self.rom, &self.platform);
+        self.invalidate_instruction_cache();
+        self.display.clear();
+        self.redraw();
+        self.program_counter = self.platform.entry_point;
+        self.i = 0;
+        self.stack.clear();
+        self.delay_timer = Timer::new();
+        self.sound_timer = Timer::new();
+        self.register = [0; 16];
+        self.keypad = [false; 16];
+        self.key_wait = None;
+        self.cycles = 0;
+        self.frame_instructions = 0;
+        self.ips_remainder = 0;
+        self.pending_watch = None;
+        self.watch_hit = None;
+        self.last_write = None;
     }
-    pub fn update(&mut self) -> Result<(), Box<dyn Error>> {
-        self.process_instructions();
-
+    /// Everything that happens at 60 Hz: the timers count down, the recorder takes a frame and
+    /// cheats and scripts run.
+    fn end_frame(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(audio) = &mut self.audio {
+            audio.set_beeping(self.sound_timer.time > 0)?;
+        }
         self.delay_timer.update();
         self.sound_timer.update();
-        self.keypad = [false; 16];
-
+        let beeping = self.is_beeping();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.capture(&self.display, beeping)?;
+        }
+        self.apply_cheats();
+        self.run_script(Script::end_frame)
+    }
+    /// Starts recording the display to an animated GIF, optionally with a WAV sidecar for the beeper.
+    pub fn start_recording(&mut self, path: &Path, audio: bool) -> Result<(), Box<dyn Error>> {
+        self.stop_recording()?;
+        self.recorder = Some(Recorder::new(path, PALETTE, SCALE, audio)?);
         Ok(())
     }
-    pub fn process_keyboard_input(&mut self, keycodes: KeyboardState) {
-        for keypress in keycodes.pressed_scancodes() {
-            match keypress {
-                Scancode::Num1 => self.keypad[0x0] = true,
-                Scancode::Num2 => self.keypad[0x1] = true,
-                Scancode::Num3 => self.keypad[0x2] = true,
-                Scancode::Num4 => self.keypad[0xC] = true,
-                Scancode::Q => self.keypad[0x4] = true,
-                Scancode::W => self.keypad[0x5] = true,
-                Scancode::E => self.keypad[0x6] = true,
-                Scancode::R => self.keypad[0xD] = true,
-                Scancode::A => self.keypad[0x7] = true,
-                Scancode::S => self.keypad[0x8] = true,
-                Scancode::D => self.keypad[0x9] = true,
-                Scancode::F => self.keypad[0xE] = true,
-                Scancode::Y | Scancode::Z => self.keypad[0xA] = true,
-                Scancode::X => self.keypad[0x0] = true,
-                Scancode::C => self.keypad[0xB] = true,
-                Scancode::V => self.keypad[0xF] = true,
-                _ => (),
-            }
+    /// Finishes the current recording, if any.
+    pub fn stop_recording(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
         }
     }
-}
-
-struct DTimer {
-    time: u32,
-}
-
-struct STimer {
-    time: u32,
-    beep_device: AudioDevice<SquareWave>,
-    playing: bool,
-}
-
-impl DTimer {
-    const fn new() -> Self {
-        DTimer { time: 0 }
+    /// Whether a recording started with [`Chip8Context::start_recording`] is running.
+    pub const fn is_recording(&self) -> bool {
+        self.recorder.is_some()
     }
-    const fn update(&mut self) {
-        if self.time > 0 {
-            self.time -= 1;
+    /// Starts writing an execution trace to `path`, see [`Tracer`] for the format.
+    pub fn start_trace(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.stop_trace()?;
+        self.tracer = Some(Tracer::new(path)?);
+        Ok(())
+    }
+    /// Finishes the current trace, if any.
+    pub fn stop_trace(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.tracer.take() {
+            Some(tracer) => tracer.finish(),
+            None => Ok(()),
         }
     }
-}
-
-impl STimer {
-    fn new(audio: &AudioSubsystem) -> Result<Self, Box<dyn Error>> {
-        let desired_spec = AudioSpecDesired {
-            freq: Some(22050),
-            channels: Some(1),
-            samples: None,
-        };
-        let beep_device = audio.open_playback(None, &desired_spec, |spec| SquareWave {
-            phase_inc: 220.0 / spec.freq as f32,
-            phase: 0.0,
-            volme: 0.1,
-        })?;
-
-        Ok(STimer {
-            time: 0,
-            beep_device,
-            playing: false,
-        })
+    /// Adds a watchpoint and returns its index. Execution is not stopped by the context itself,
+    /// the caller checks [`Chip8Context::take_watch_hit`] after every step.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.push(watchpoint);
+        self.watchpoints.len() - 1
     }
-    fn update(&mut self) {
-        if self.time > 0 {
-            if !self.playing {
-                self.playing = true;
-                self.beep_device.resume();
-            }
-            self.time -= 1;
+    /// Removes the watchpoint at `index`; the indices of later watchpoints shift down by one.
+    pub fn remove_watchpoint(&mut self, index: usize) -> Option<Watchpoint> {
+        (index < self.watchpoints.len()).then(|| self.watchpoints.remove(index))
+    }
+    /// The active watchpoints, in the order they were added.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+    /// The watchpoint triggered by the last executed instruction, if any.
+    pub const fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+    /// Sets the title of the emulator window, if there is one.
+    pub fn set_window_title(&mut self, title: &str) -> Result<(), Box<dyn Error>> {
+        match &mut self.video {
+            Some(video) => video.set_title(title),
+            None => Ok(()),
+        }
+    }
+    /// Writes the current display to a PNG file using the default palette and scale.
+    pub fn save_screenshot(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        screenshot::save_png(&self.display, PALETTE, SCALE, path)
+    }
+    /// Changes the interpreter behaviours, see [`Quirks`]. Takes effect with the next instruction.
+    pub const fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+    /// Sets the instructions per second, at least 60. When they are not a multiple of 60, frames
+    /// run one instruction more now and then, e.g. 700 runs as 11 or 12 per frame.
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips.max(pacing::FRAME_RATE);
+    }
+    /// The instructions per second set with [`Chip8Context::set_ips`].
+    pub const fn ips(&self) -> u32 {
+        self.ips
+    }
+    /// Sets the instructions per second to `instructions` per 60 Hz frame.
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        self.set_ips(instructions.saturating_mul(pacing::FRAME_RATE));
+    }
+    /// The number of instructions in the current 60 Hz frame, after which the timers count down.
+    /// This is `ips / 60`, plus one in the frames that catch up on the remainder.
+    pub const fn instructions_per_frame(&self) -> u32 {
+        (self.ips_due() / pacing::FRAME_RATE as u64) as u32
+    }
+    /// `ips` plus the carried remainder, widened so that it cannot overflow near `u32::MAX`.
+    const fn ips_due(&self) -> u64 {
+        self.ips as u64 + self.ips_remainder as u64
+    }
+    /// The number of instructions until the current frame ends, at least one.
+    pub const fn instructions_left_in_frame(&self) -> u32 {
+        let frame = self.instructions_per_frame();
+        if self.frame_instructions < frame {
+            frame - self.frame_instructions
         } else {
-            self.playing = false;
-            self.beep_device.pause();
+            1
         }
     }
-}
-
-pub struct Renderer {
-    canvas: WindowCanvas,
-    to_be_rendered: Vec<Point>,
-}
-
-impl Renderer {
-    pub fn new(window: Window) -> Result<Self, Box<dyn Error>> {
-        let mut canvas = window.into_canvas().accelerated().build()?;
-        canvas.set_logical_size(LOGICAL_WINDOW_SIZE.0, LOGICAL_WINDOW_SIZE.1)?;
-        let to_be_rendered = Vec::new();
-
-        Ok(Renderer {
-            canvas,
-            to_be_rendered,
-        })
+    /// Starts counting executed instructions, see [`Profiler`].
+    pub fn enable_profiler(&mut self) {
+        let memory_size = self.memory.len();
+        self.profiler
+            .get_or_insert_with(|| Profiler::new(memory_size));
     }
-
-    fn draw(&mut self) -> Result<(), Box<dyn Error>> {
-        self.canvas.set_draw_color(Color::WHITE);
-        for point in &self.to_be_rendered {
-            self.canvas.draw_point(*point)?;
+    /// The profiler, if [`Chip8Context::enable_profiler`] was called.
+    pub const fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+    /// Starts tracking which program bytes are used as code and data, see [`Coverage`].
+    pub fn enable_coverage(&mut self) {
+        let (start, program_size) = (self.platform.load_address, self.rom.len());
+        let memory_size = self.memory.len();
+        self.coverage
+            .get_or_insert_with(|| Coverage::new(start, program_size, memory_size));
+    }
+    /// The coverage, if [`Chip8Context::enable_coverage`] was called.
+    pub const fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+    /// Keeps decoded instructions by address instead of decoding them on every execution.
+    pub fn enable_instruction_cache(&mut self) {
+        let memory_size = self.memory.len();
+        self.instruction_cache
+            .get_or_insert_with(|| InstructionCache::new(memory_size));
+    }
+    /// Forgets all decoded instructions, after memory was replaced as a whole.
+    pub(crate) fn invalidate_instruction_cache(&mut self) {
+        if let Some(cache) = &mut self.instruction_cache {
+            cache.clear();
         }
-
-        self.canvas.present();
-        Ok(())
+    }
+    /// All of memory, including the font and the program. Its size depends on the platform.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+    /// Masks an address into memory, see [`Platform::address_mask`].
+    pub(crate) const fn address_mask(&self) -> usize {
+        self.platform.address_mask()
+    }
+    /// Hex digits needed to print any address: three for 4 KiB of memory, four for more.
+    pub(crate) const fn address_digits(&self) -> usize {
+        if self.memory.len() > 0x1000 { 4 } else { 3 }
+    }
+    /// The address of the next instruction.
+    pub const fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+    /// The index register `I`.
+    pub const fn index(&self) -> u16 {
+        self.i
+    }
+    /// The registers `V0` to `VF`.
+    pub const fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+    /// The return addresses of the active subroutine calls, the innermost last.
+    pub fn stack(&self) -> &[usize] {
+        &self.stack
+    }
+    /// The delay timer in 60 Hz ticks.
+    pub const fn delay_timer(&self) -> u32 {
+        self.delay_timer.time
+    }
+    /// The sound timer in 60 Hz ticks; the beeper sounds while it is above zero.
+    pub const fn sound_timer(&self) -> u32 {
+        self.sound_timer.time
+    }
+    /// The number of instructions executed since the ROM was loaded or reset.
+    pub const fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    /// The interpreter behaviours in effect.
+    pub const fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+    /// The memory map the program was loaded with.
+    pub const fn platform(&self) -> Platform {
+        self.platform
+    }
+    /// Writes one byte of memory, e.g. from a debugger or a cheat.
+    pub fn write_memory(&mut self, address: usize, value: u8) {
+        let address = address & self.address_mask();
+        self.memory[address] = value;
+        self.load_mapped_display(address, 1);
+        self.load_memory_stack(address, 1);
+        if let Some(cache) = &mut self.instruction_cache {
+            cache.invalidate(address, 1);
+        }
+    }
+    /// The current contents of the screen.
+    pub const fn display(&self) -> &Framebuffer {
+        &self.display
+    }
+    /// Whether the beeper sounds, i.e. the sound timer is running.
+    pub const fn is_beeping(&self) -> bool {
+        self.sound_timer.time > 0
+    }
+    /// Presses or releases keypad key `key` (0x0 - 0xF). It stays in that state until changed.
+    pub const fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keypad[key as usize & 0xF] = pressed;
+    }
+    /// Takes the state of every key from `input`.
+    pub fn process_input(&mut self, input: &mut dyn InputSource) {
+        self.keypad = input.keypad();
+    }
+    /// The held keypad keys, indexed by key.
+    pub const fn keypad(&self) -> &[bool; 16] {
+        &self.keypad
     }
 }
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volme: f32,
+struct Timer {
+    time: u32,
 }
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
-
-    fn callback(&mut self, out: &mut [Self::Channel]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volme
-            } else {
-                -self.volme
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+impl Timer {
+    const fn new() -> Self {
+        Timer { time: 0 }
+    }
+    const fn update(&mut self) {
+        if self.time > 0 {
+            self.time -= 1;
         }
     }
 }
 
-const FONT_OFFSET: usize = 0x050;
-const INSTR_OFFSET: usize = 0x200;
+const SAMPLE_RATE: i32 = 22050;
+pub const BEEP_FREQUENCY: f32 = 220.0;
+pub const BEEP_VOLUME: f32 = 0.1;
+
 const FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -548,20 +919,16 @@ const FONT: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-fn init_memory(program_bytes: Vec<u8>) -> [u8; 4096] {
-    let mut memory = [0; 4096];
+fn init_memory(program_bytes: &[u8], platform: &Platform) -> Box<[u8]> {
+    let mut memory = vec![0; platform.memory_size].into_boxed_slice();
 
-    // Skip 0x000 - 0x1FF
+    // Font, 0x050 - 0x09F on most platforms
+    let font = platform.font_address;
+    memory[font..(FONT.len() + font)].copy_from_slice(&FONT[..]);
 
-    // Font 0x050 - 0x0A0
-    memory[FONT_OFFSET..(FONT.len() + FONT_OFFSET)].copy_from_slice(&FONT[..]);
-
-    // INST 0x200 - 0xFFF
-    memory[INSTR_OFFSET..(program_bytes.len() + INSTR_OFFSET)].copy_from_slice(&program_bytes[..]);
+    // Program, 0x200 - 0xFFF on most platforms and up to 0xFFFF on XO-CHIP
+    let load = platform.load_address;
+    memory[load..(program_bytes.len() + load)].copy_from_slice(program_bytes);
 
     memory
 }
-
-const fn bit_i(byte: u16, i: u16) -> u8 {
-    ((byte >> (12 - i * 4)) & 0xF) as u8
-}
@@ -1,46 +1,192 @@
-use chip_8::{Chip8Context, Renderer};
-use sdl2::{event::Event, keyboard::Keycode};
-use std::{env::args, error::Error, thread::sleep, time::Duration};
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    let audio_subsystem = sdl_context.audio()?;
-    let window = video_subsystem
-        .window("CHIP-8", chip_8::WINDOW_SIZE.0, chip_8::WINDOW_SIZE.1)
-        .position_centered()
-        .vulkan()
-        .build()?;
-    let mut event_pump = sdl_context.event_pump()?;
-
-    let args: Vec<String> = args().collect();
-    if args.len() < 2 {
-        println!("No CHIP-8 file supplied as an argument!");
+use chip_8::{
+    Chip8Context,
+    cheats::load_cheat_file,
+    pacing::{FRAME_RATE, FramePacer},
+    rom::{Rom, RomFormat},
+    screenshot,
+    terminal::{Terminal, TerminalBell, TerminalScreen},
+};
+use cli::Options;
+use counting_allocator::CountingAllocator;
+use std::{env::args, error::Error, path::Path, process::exit, thread::sleep, time::Instant};
+#[cfg(feature = "sdl")]
+use window::run_window;
+
+mod cli;
+mod counting_allocator;
+#[cfg(feature = "sdl")]
+mod window;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+
+fn main() {
+    // Errors such as an invalid ROM are reported by their message rather than `Debug`.
+    if let Err(error) = run() {
+        println!("{error}");
+        exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(args().skip(1))?;
+    let rom = Rom::load(Path::new(&options.rom_path))?;
+    let platform = options.platform(rom.format);
+    platform.check_program(&rom.program)?;
+    if rom.format != RomFormat::Chip8 {
+        println!(
+            "This ROM is for {}; only its CHIP-8 instructions are supported",
+            rom.format
+        );
+    }
+    let file = rom.program;
+
+    if let Some(instructions) = options.bench_instructions {
+        let chip_8_context = Chip8Context::builder(file).platform(platform).build()?;
+        return bench(chip_8_context, &options, instructions);
+    }
+
+    if let Some(instructions) = options.headless_instructions {
+        let mut chip_8_context = Chip8Context::builder(file).platform(platform).build()?;
+        start(&mut chip_8_context, &options)?;
+        for _ in 0..instructions {
+            chip_8_context.step()?;
+        }
+        finish(&mut chip_8_context, &options)?;
+        if options.screenshot {
+            let path = screenshot::timestamped_path();
+            chip_8_context.save_screenshot(&path)?;
+            println!("Saved screenshot to {}", path.display());
+        }
         return Ok(());
     }
-    let file = std::fs::read(&args[1]).expect("Invalid file path!");
-
-    let renderer = Renderer::new(window)?;
-    let mut chip_8_context = Chip8Context::new(renderer, &audio_subsystem, file)?;
-
-    'running: loop {
-        chip_8_context.process_keyboard_input(event_pump.keyboard_state());
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::ESCAPE),
-                    ..
-                } => break 'running,
-                _ => (),
+
+    if let Some(style) = options.terminal {
+        let mut terminal = Terminal::new()?;
+        let screen = TerminalScreen::new(style);
+        let mut chip_8_context = Chip8Context::builder(file)
+            .platform(platform)
+            .video(screen)
+            .audio(TerminalBell::default())
+            .build()?;
+        start(&mut chip_8_context, &options)?;
+        let mut pacer = FramePacer::new();
+        'terminal: loop {
+            for _ in 0..pacer.frames_due(1.0) {
+                for _ in 0..chip_8_context.instructions_left_in_frame() {
+                    if !terminal.poll_events()? {
+                        break 'terminal;
+                    }
+                    chip_8_context.process_input(&mut terminal);
+                    chip_8_context.step()?;
+                }
             }
+            chip_8_context.present()?;
+            sleep(pacer.time_to_next_frame(1.0));
         }
+        drop(terminal);
+        return finish(&mut chip_8_context, &options);
+    }
+
+    run_window(file, platform, &options)
+}
+
+/// Without SDL there is no window, only the terminal and the headless modes.
+#[cfg(not(feature = "sdl"))]
+fn run_window(
+    _file: Vec<u8>,
+    _platform: chip_8::platform::Platform,
+    _options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    Err("Built without the sdl feature; run with --terminal, --braille or --headless".into())
+}
 
-        chip_8_context.update()?;
-        sleep(Duration::from_nanos(
-            (1_000_000_000 / chip_8::TARGET_IPS) as u64,
-        ));
+/// Runs `instructions` instructions as fast as possible and reports the interpreter's speed.
+fn bench(
+    mut chip_8_context: Chip8Context,
+    options: &Options,
+    instructions: u64,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(count) = options.instructions_per_frame {
+        chip_8_context.set_instructions_per_frame(count);
+    }
+    if options.decode_cache {
+        chip_8_context.enable_instruction_cache();
     }
 
+    let (allocations_before, bytes_before) = ALLOCATOR.totals();
+    let start = Instant::now();
+    for _ in 0..instructions {
+        chip_8_context.step()?;
+    }
+    let elapsed = start.elapsed();
+    let (allocations_after, bytes_after) = ALLOCATOR.totals();
+
+    let seconds = elapsed.as_secs_f64();
+    let instructions_per_second = instructions as f64 / seconds;
+    let frames_per_second =
+        instructions_per_second * f64::from(FRAME_RATE) / f64::from(chip_8_context.ips());
+    println!("Instructions: {instructions}");
+    println!("Time: {seconds:.3} s");
+    println!("Instructions per second: {instructions_per_second:.0}");
+    println!("Frames per second: {frames_per_second:.0}");
+    println!(
+        "Allocations: {} ({} bytes)",
+        allocations_after - allocations_before,
+        bytes_after - bytes_before
+    );
+    Ok(())
+}
+
+fn start(chip_8_context: &mut Chip8Context, options: &Options) -> Result<(), Box<dyn Error>> {
+    if let Some(instructions) = options.instructions_per_frame {
+        chip_8_context.set_instructions_per_frame(instructions);
+    }
+    if options.decode_cache {
+        chip_8_context.enable_instruction_cache();
+    }
+    if let Some(path) = &options.record_path {
+        chip_8_context.start_recording(path, options.record_audio)?;
+    }
+    if options.profile {
+        chip_8_context.enable_profiler();
+    }
+    if options.coverage_path.is_some() {
+        chip_8_context.enable_coverage();
+    }
+    if let Some(path) = &options.trace_path {
+        chip_8_context.start_trace(path)?;
+    }
+    let path = options.cheat_file();
+    if options.cheats_path.is_some() || path.exists() {
+        let cheats = load_cheat_file(&path)?;
+        println!("Loaded {} cheats from {}", cheats.len(), path.display());
+        for cheat in cheats {
+            chip_8_context.add_cheat(cheat);
+        }
+    }
+    if let Some(path) = &options.script_path {
+        chip_8_context.load_script(path)?;
+    }
+    Ok(())
+}
+
+fn finish(chip_8_context: &mut Chip8Context, options: &Options) -> Result<(), Box<dyn Error>> {
+    chip_8_context.stop_recording()?;
+    chip_8_context.stop_trace()?;
+    if let Some(profiler) = chip_8_context.profiler() {
+        print!(
+            "{}",
+            profiler.report(chip_8_context.memory(), chip_8_context.ips(), 20)
+        );
+        if let Some(path) = &options.profile_path {
+            profiler.export(path)?;
+            println!("Saved profile to {}", path.display());
+        }
+    }
+    if let (Some(coverage), Some(path)) = (chip_8_context.coverage(), &options.coverage_path) {
+        coverage.export(path, chip_8_context.memory())?;
+        println!("Saved coverage to {}", path.display());
+    }
     Ok(())
 }
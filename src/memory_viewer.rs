@@ -0,0 +1,273 @@
+use crate::{Chip8Context, FONT};
+use sdl2::{
+    VideoSubsystem, event::Event, keyboard::Keycode, mouse::MouseButton, pixels::Color, rect::Rect,
+    render::WindowCanvas,
+};
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+// Everything is drawn with the built-in 4x5 font, so one glyph pixel is `SCALE` window pixels.
+const SCALE: i32 = 3;
+const CHAR_WIDTH: i32 = 5 * SCALE;
+const ROW_HEIGHT: i32 = 7 * SCALE;
+const MARGIN: i32 = 2 * SCALE;
+const BYTES_PER_ROW: usize = 16;
+const VISIBLE_ROWS: usize = 32;
+// Two digits and a gap per byte, after the address.
+const BYTE_COLUMNS: i32 = BYTES_PER_ROW as i32 * 3 - 1;
+
+const FRAME_TIME: Duration = Duration::from_millis(16);
+const FLASH_FRAMES: u8 = 30;
+
+const ADDRESS_COLOR: Color = Color::RGB(0x80, 0x80, 0x80);
+const PC_COLOR: Color = Color::RGB(0x00, 0x80, 0x00);
+const INDEX_COLOR: Color = Color::RGB(0x00, 0x40, 0xC0);
+const CHANGED_COLOR: Color = Color::RGB(0xC0, 0xC0, 0x00);
+const CURSOR_COLOR: Color = Color::RGB(0xC0, 0x00, 0x00);
+
+/// A second window with a hex dump of memory. PC and I are highlighted and changed bytes flash.
+/// While the emulator is paused, a byte can be selected with the mouse or arrow keys and overwritten
+/// by typing two hex digits.
+pub struct MemoryViewer {
+    canvas: WindowCanvas,
+    top_row: usize,
+    cursor: Option<usize>,
+    high_nibble: Option<u8>,
+    previous: Vec<u8>,
+    flashing: Vec<u8>,
+    // Three digits for 4 KiB of memory, four for more.
+    address_digits: u32,
+    last_draw: Option<Instant>,
+}
+
+impl MemoryViewer {
+    pub fn new(video: &VideoSubsystem, context: &Chip8Context) -> Result<Self, Box<dyn Error>> {
+        let memory = context.memory();
+        let address_digits = context.address_digits() as u32;
+        let width = 2 * MARGIN + (address_digits as i32 + 1 + BYTE_COLUMNS) * CHAR_WIDTH;
+        let height = 2 * MARGIN + VISIBLE_ROWS as i32 * ROW_HEIGHT;
+        let window = video
+            .window("CHIP-8 memory", width as u32, height as u32)
+            .build()?;
+        let canvas = window.into_canvas().accelerated().build()?;
+
+        let mut viewer = MemoryViewer {
+            canvas,
+            top_row: 0,
+            cursor: None,
+            high_nibble: None,
+            previous: memory.to_vec(),
+            flashing: vec![0; memory.len()],
+            address_digits,
+            last_draw: None,
+        };
+        viewer.scroll_to(context.program_counter());
+        Ok(viewer)
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// Handles scrolling, selection and editing. Events for other windows are ignored.
+    pub fn handle_event(&mut self, event: &Event, context: &mut Chip8Context, paused: bool) {
+        let own_window = self.window_id();
+        match *event {
+            Event::MouseWheel { window_id, y, .. } if window_id == own_window => {
+                self.scroll(-3 * y as isize);
+            }
+            Event::MouseButtonDown {
+                window_id,
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if window_id == own_window => {
+                self.cursor = self.address_at(x, y);
+                self.high_nibble = None;
+            }
+            Event::KeyDown {
+                window_id,
+                keycode: Some(keycode),
+                ..
+            } if window_id == own_window => match keycode {
+                Keycode::UP => self.move_cursor_or_scroll(-(BYTES_PER_ROW as isize), -1),
+                Keycode::DOWN => self.move_cursor_or_scroll(BYTES_PER_ROW as isize, 1),
+                Keycode::LEFT => self.move_cursor_or_scroll(-1, 0),
+                Keycode::RIGHT => self.move_cursor_or_scroll(1, 0),
+                Keycode::PAGEUP => self.scroll(-(VISIBLE_ROWS as isize)),
+                Keycode::PAGEDOWN => self.scroll(VISIBLE_ROWS as isize),
+                Keycode::HOME => self.scroll_to(context.program_counter()),
+                _ if paused => self.edit(keycode, context),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    /// Redraws the window, at most 60 times per second.
+    pub fn draw(&mut self, context: &Chip8Context) -> Result<(), Box<dyn Error>> {
+        if self
+            .last_draw
+            .is_some_and(|last_draw| last_draw.elapsed() < FRAME_TIME)
+        {
+            return Ok(());
+        }
+        self.last_draw = Some(Instant::now());
+
+        let memory = context.memory();
+        for ((byte, previous), flashing) in
+            memory.iter().zip(&self.previous).zip(&mut self.flashing)
+        {
+            *flashing = if byte != previous {
+                FLASH_FRAMES
+            } else {
+                flashing.saturating_sub(1)
+            };
+        }
+        self.previous.copy_from_slice(memory);
+
+        let pc = context.program_counter();
+        let index = context.index() as usize;
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        let byte_column = self.address_digits as i32 + 1;
+        for row in 0..VISIBLE_ROWS.min(self.total_rows() - self.top_row) {
+            let row_address = (self.top_row + row) * BYTES_PER_ROW;
+            let y = MARGIN + row as i32 * ROW_HEIGHT + SCALE;
+            self.draw_hex(row_address, self.address_digits, MARGIN, y, ADDRESS_COLOR)?;
+
+            for column in 0..BYTES_PER_ROW {
+                let address = row_address + column;
+                let x = MARGIN + (byte_column + column as i32 * 3) * CHAR_WIDTH;
+
+                // Blink for the duration of the flash.
+                let flashing = (self.flashing[address] / 5) % 2 == 1;
+                let highlight = if self.cursor == Some(address) {
+                    Some(CURSOR_COLOR)
+                } else if address == pc || address == pc + 1 {
+                    Some(PC_COLOR)
+                } else if address == index {
+                    Some(INDEX_COLOR)
+                } else if flashing {
+                    Some(CHANGED_COLOR)
+                } else {
+                    None
+                };
+                if let Some(highlight) = highlight {
+                    self.canvas.set_draw_color(highlight);
+                    self.canvas.fill_rect(Rect::new(
+                        x - SCALE,
+                        y - SCALE,
+                        (2 * CHAR_WIDTH + SCALE) as u32,
+                        ROW_HEIGHT as u32,
+                    ))?;
+                }
+
+                let text_color = if flashing { Color::BLACK } else { Color::WHITE };
+                self.draw_hex(memory[address] as usize, 2, x, y, text_color)?;
+            }
+        }
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn draw_hex(
+        &mut self,
+        value: usize,
+        digits: u32,
+        x: i32,
+        y: i32,
+        color: Color,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut pixels = Vec::new();
+        for digit in 0..digits {
+            let nibble = (value >> (4 * (digits - digit - 1))) & 0xF;
+            let glyph_x = x + digit as i32 * CHAR_WIDTH;
+            for (glyph_y, bits) in FONT[nibble * 5..nibble * 5 + 5].iter().enumerate() {
+                for bit in 0..4 {
+                    if bits & (0x80 >> bit) != 0 {
+                        pixels.push(Rect::new(
+                            glyph_x + bit * SCALE,
+                            y + glyph_y as i32 * SCALE,
+                            SCALE as u32,
+                            SCALE as u32,
+                        ));
+                    }
+                }
+            }
+        }
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rects(&pixels)?;
+        Ok(())
+    }
+
+    fn edit(&mut self, keycode: Keycode, context: &mut Chip8Context) {
+        let Some(address) = self.cursor else {
+            return;
+        };
+        let name = keycode.name();
+        if name.len() != 1 {
+            return;
+        }
+        let Ok(nibble) = u8::from_str_radix(&name, 16) else {
+            return;
+        };
+
+        match self.high_nibble.take() {
+            None => self.high_nibble = Some(nibble),
+            Some(high) => {
+                context.write_memory(address, (high << 4) | nibble);
+                self.move_cursor_or_scroll(1, 0);
+            }
+        }
+    }
+
+    fn address_at(&self, x: i32, y: i32) -> Option<usize> {
+        let column = (x - MARGIN) / CHAR_WIDTH - (self.address_digits as i32 + 1);
+        let row = (y - MARGIN) / ROW_HEIGHT;
+        if x < MARGIN || y < MARGIN || column < 0 || column % 3 == 2 {
+            return None;
+        }
+        let address = (self.top_row + row as usize) * BYTES_PER_ROW + column as usize / 3;
+        (address < self.previous.len()).then_some(address)
+    }
+
+    fn move_cursor_or_scroll(&mut self, bytes: isize, rows: isize) {
+        match self.cursor {
+            Some(address) => {
+                let address = address
+                    .saturating_add_signed(bytes)
+                    .min(self.previous.len() - 1);
+                self.cursor = Some(address);
+                self.high_nibble = None;
+                let row = address / BYTES_PER_ROW;
+                if row < self.top_row || row >= self.top_row + VISIBLE_ROWS {
+                    self.scroll_to(address);
+                }
+            }
+            None => self.scroll(rows),
+        }
+    }
+
+    fn scroll(&mut self, rows: isize) {
+        self.top_row = self
+            .top_row
+            .saturating_add_signed(rows)
+            .min(self.total_rows() - VISIBLE_ROWS);
+    }
+
+    /// Scrolls so that `address` is in the upper part of the window.
+    fn scroll_to(&mut self, address: usize) {
+        self.top_row = (address / BYTES_PER_ROW)
+            .saturating_sub(VISIBLE_ROWS / 4)
+            .min(self.total_rows() - VISIBLE_ROWS);
+    }
+
+    fn total_rows(&self) -> usize {
+        self.previous.len() / BYTES_PER_ROW
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+pub const FRAME_RATE: u32 = 60;
+pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / FRAME_RATE as u64);
+// After a stall, e.g. while the window is dragged, skip ahead instead of racing to catch up.
+const MAX_FRAMES_BEHIND: u32 = 5;
+
+/// Schedules 60 Hz frames against a monotonic clock. Time that passes between calls is collected in
+/// an accumulator, so sleep granularity and rendering time do not slow the emulation down.
+pub struct FramePacer {
+    last: Instant,
+    accumulator: Duration,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        FramePacer {
+            last: Instant::now(),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// The number of frames due since the last call when running at `speed` times real time.
+    pub fn frames_due(&mut self, speed: f64) -> u32 {
+        let now = Instant::now();
+        self.accumulator += now.duration_since(self.last).mul_f64(speed);
+        self.last = now;
+        self.accumulator = self.accumulator.min(FRAME_DURATION * MAX_FRAMES_BEHIND);
+
+        let frames = (self.accumulator.as_nanos() / FRAME_DURATION.as_nanos()) as u32;
+        self.accumulator -= FRAME_DURATION * frames;
+        frames
+    }
+
+    /// The real time until the next frame is due at `speed` times real time.
+    pub fn time_to_next_frame(&self, speed: f64) -> Duration {
+        FRAME_DURATION
+            .saturating_sub(self.accumulator)
+            .div_f64(speed)
+            .saturating_sub(self.last.elapsed())
+    }
+
+    /// Forgets the time passed so far, e.g. after being paused.
+    pub fn reset(&mut self) {
+        self.last = Instant::now();
+        self.accumulator = Duration::ZERO;
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    FONT,
+    rom::{RomError, RomFormat},
+    stack::StackModel,
+};
+
+/// The memory map of a machine that ran CHIP-8: where the font lives, where programs are loaded
+/// and where execution starts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Platform {
+    pub name: &'static str,
+    /// Address of the 4x5 hex digit font used by `FX29`.
+    pub font_address: usize,
+    /// Where the program is copied to.
+    pub load_address: usize,
+    /// The address of the first instruction, usually the load address.
+    pub entry_point: usize,
+    /// The end of the memory available to programs (exclusive).
+    pub program_end: usize,
+    /// The size of memory, a power of two from 4 KiB to 64 KiB. Addresses wrap around at its end.
+    pub memory_size: usize,
+    pub stack: StackModel,
+    /// Where the display is mapped into memory, one bit per pixel and the leftmost pixel of every
+    /// byte in its most significant bit. Writes to that memory show on screen and programs can
+    /// read what was drawn.
+    pub display_address: Option<usize>,
+}
+
+impl Platform {
+    /// The layout most modern interpreters and ROMs expect.
+    pub const CHIP_8: Platform = Platform {
+        name: "chip8",
+        font_address: 0x050,
+        load_address: 0x200,
+        entry_point: 0x200,
+        program_end: 0x1000,
+        memory_size: 0x1000,
+        stack: StackModel::Host { depth: 16 },
+        display_address: None,
+    };
+    /// The original COSMAC VIP interpreter, which kept a 12 level stack and its variables from
+    /// 0xEA0 and the display from 0xF00.
+    pub const COSMAC_VIP: Platform = Platform {
+        name: "vip",
+        font_address: 0x050,
+        load_address: 0x200,
+        entry_point: 0x200,
+        program_end: 0xEA0,
+        memory_size: 0x1000,
+        stack: StackModel::Memory {
+            top: 0xED0,
+            depth: 12,
+        },
+        display_address: Some(0xF00),
+    };
+    /// The ETI-660, which loads programs at 0x600.
+    pub const ETI_660: Platform = Platform {
+        name: "eti660",
+        font_address: 0x050,
+        load_address: 0x600,
+        entry_point: 0x600,
+        program_end: 0x1000,
+        memory_size: 0x1000,
+        stack: StackModel::Host { depth: 16 },
+        display_address: None,
+    };
+    /// CHIP-8X, which loads programs at 0x300.
+    pub const CHIP_8X: Platform = Platform {
+        name: "chip8x",
+        font_address: 0x050,
+        load_address: 0x300,
+        entry_point: 0x300,
+        program_end: 0x1000,
+        memory_size: 0x1000,
+        stack: StackModel::Host { depth: 16 },
+        display_address: None,
+    };
+    /// XO-CHIP, which has 64 KiB of memory for larger programs.
+    pub const XO_CHIP: Platform = Platform {
+        name: "xochip",
+        font_address: 0x050,
+        load_address: 0x200,
+        entry_point: 0x200,
+        program_end: 0x10000,
+        memory_size: 0x10000,
+        stack: StackModel::Host { depth: 16 },
+        display_address: None,
+    };
+    pub const ALL: [Platform; 5] = [
+        Platform::CHIP_8,
+        Platform::COSMAC_VIP,
+        Platform::ETI_660,
+        Platform::CHIP_8X,
+        Platform::XO_CHIP,
+    ];
+
+    /// Looks up a platform by name, e.g. `"eti660"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The platform ROMs of `format` are usually written for.
+    pub const fn for_format(format: RomFormat) -> Self {
+        match format {
+            RomFormat::Chip8X => Platform::CHIP_8X,
+            RomFormat::XoChip => Platform::XO_CHIP,
+            RomFormat::Chip8 | RomFormat::SuperChip => Platform::CHIP_8,
+        }
+    }
+
+    /// Loads programs at `address` and starts executing them there.
+    pub const fn with_load_address(self, address: usize) -> Self {
+        Platform {
+            load_address: address,
+            entry_point: address,
+            ..self
+        }
+    }
+
+    pub const fn with_entry_point(self, address: usize) -> Self {
+        Platform {
+            entry_point: address,
+            ..self
+        }
+    }
+
+    /// Allows `depth` nested calls before a [`StackError::Overflow`](crate::stack::StackError).
+    pub const fn with_stack_depth(self, depth: usize) -> Self {
+        Platform {
+            stack: self.stack.with_depth(depth),
+            ..self
+        }
+    }
+
+    /// Masks an address into memory, wrapping it around at the end.
+    pub const fn address_mask(&self) -> usize {
+        self.memory_size - 1
+    }
+
+    /// Checks that `program` is not empty and fits between the load address and the end of
+    /// program memory, that a whole instruction can be fetched from the entry point, that the font
+    /// fits into memory and that the stack does not overlap the program.
+    pub const fn check_program(&self, program: &[u8]) -> Result<(), RomError> {
+        if !self.memory_size.is_power_of_two()
+            || self.memory_size < 0x1000
+            || self.memory_size > 0x10000
+        {
+            return Err(RomError::InvalidMemorySize {
+                size: self.memory_size,
+            });
+        }
+        let max_depth = self.stack.max_depth(self.program_end);
+        if self.stack.depth() > max_depth {
+            return Err(RomError::StackTooDeep {
+                depth: self.stack.depth(),
+                max: max_depth,
+            });
+        }
+        if self.entry_point > self.memory_size - 2 {
+            return Err(RomError::InvalidEntryPoint {
+                address: self.entry_point,
+            });
+        }
+        if self.font_address > self.memory_size - FONT.len() {
+            return Err(RomError::InvalidFontAddress {
+                address: self.font_address,
+            });
+        }
+        let end = if self.program_end < self.memory_size {
+            self.program_end
+        } else {
+            self.memory_size
+        };
+        let max = end.saturating_sub(self.load_address);
+        match program.len() {
+            0 => Err(RomError::Empty),
+            size if size > max => Err(RomError::TooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Platform::CHIP_8
+    }
+}
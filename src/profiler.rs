@@ -0,0 +1,153 @@
+use crate::{disassembler::opcode_class, instruction::read_opcode};
+use std::{collections::BTreeMap, error::Error, fmt::Write as _, fs, path::Path};
+
+/// Counts executed instructions per address and per opcode class.
+pub struct Profiler {
+    address_counts: Vec<u64>,
+    opcode_counts: BTreeMap<&'static str, u64>,
+    wait_instructions: u64,
+    last_address: Option<usize>,
+    total: u64,
+}
+
+impl Profiler {
+    /// A profiler for a machine with `memory_size` bytes of memory.
+    pub fn new(memory_size: usize) -> Self {
+        Profiler {
+            address_counts: vec![0; memory_size],
+            opcode_counts: BTreeMap::new(),
+            wait_instructions: 0,
+            last_address: None,
+            total: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, address: usize, opcode: u16, memory: &[u8]) {
+        let class = opcode_class(opcode);
+        self.address_counts[address] += 1;
+        *self.opcode_counts.entry(class).or_default() += 1;
+        self.total += 1;
+
+        // Wait loops are recognised by their shape: FX0A repeating because no key is pressed,
+        // a jump to itself, or a short loop jumping back to an FX07 that polls the delay timer.
+        let target = (opcode & 0xFFF) as usize;
+        match class {
+            "FX0A" if self.last_address == Some(address) => self.wait_instructions += 1,
+            "1NNN" if target == address => self.wait_instructions += 1,
+            "1NNN"
+                if target < address
+                    && address - target <= 6
+                    && memory[target] & 0xF0 == 0xF0
+                    && memory[target + 1] == 0x07 =>
+            {
+                self.wait_instructions += ((address - target) / 2 + 1) as u64;
+            }
+            _ => (),
+        }
+        self.last_address = Some(address);
+    }
+
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Instructions executed as part of a detected wait loop.
+    pub const fn wait_instructions(&self) -> u64 {
+        self.wait_instructions
+    }
+
+    /// Executed addresses with their counts, most executed first.
+    pub fn hottest_addresses(&self) -> Vec<(usize, u64)> {
+        let mut addresses: Vec<(usize, u64)> = self
+            .address_counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(address, &count)| (address, count))
+            .collect();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses
+    }
+
+    /// Opcode classes with their counts, most executed first.
+    pub fn opcode_histogram(&self) -> Vec<(&'static str, u64)> {
+        let mut opcodes: Vec<(&'static str, u64)> =
+            self.opcode_counts.iter().map(|(&k, &v)| (k, v)).collect();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        opcodes
+    }
+
+    /// A human readable summary of the `top` hottest addresses, the opcode histogram and wait loops.
+    pub fn report(&self, memory: &[u8], ips: u32, top: usize) -> String {
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+        let mut report = String::new();
+
+        let _ = writeln!(report, "Profile of {} instructions", self.total);
+        let _ = writeln!(report, "\nHottest addresses:");
+        for (address, count) in self.hottest_addresses().into_iter().take(top) {
+            let opcode = read_opcode(memory, address);
+            let _ = writeln!(
+                report,
+                "  {address:#05X}  {opcode:04X}  {count:>12}  {:>5.1}%",
+                percent(count)
+            );
+        }
+        let _ = writeln!(report, "\nOpcodes:");
+        for (class, count) in self.opcode_histogram() {
+            let _ = writeln!(report, "  {class:<8} {count:>12}  {:>5.1}%", percent(count));
+        }
+        let _ = writeln!(
+            report,
+            "\nWait loops: {} instructions ({:.1}%, about {:.2} s at {ips} IPS)",
+            self.wait_instructions,
+            percent(self.wait_instructions),
+            self.wait_instructions as f64 / ips as f64
+        );
+        report
+    }
+
+    /// Exports all counts to `path`, as JSON if it ends in `.json` and as CSV otherwise.
+    pub fn export(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let contents = if json { self.to_json() } else { self.to_csv() };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,key,count\n");
+        let _ = writeln!(csv, "summary,total,{}", self.total);
+        let _ = writeln!(csv, "summary,wait_loops,{}", self.wait_instructions);
+        for (address, count) in self.hottest_addresses() {
+            let _ = writeln!(csv, "address,{address:#05X},{count}");
+        }
+        for (class, count) in self.opcode_histogram() {
+            let _ = writeln!(csv, "opcode,{class},{count}");
+        }
+        csv
+    }
+
+    fn to_json(&self) -> String {
+        let addresses: Vec<String> = self
+            .hottest_addresses()
+            .into_iter()
+            .map(|(address, count)| {
+                format!("{{\"address\": \"{address:#05X}\", \"count\": {count}}}")
+            })
+            .collect();
+        let opcodes: Vec<String> = self
+            .opcode_histogram()
+            .into_iter()
+            .map(|(class, count)| format!("\"{class}\": {count}"))
+            .collect();
+        format!(
+            "{{\n  \"total\": {},\n  \"wait_loops\": {},\n  \"addresses\": [\n    {}\n  ],\n  \"opcodes\": {{\n    {}\n  }}\n}}\n",
+            self.total,
+            self.wait_instructions,
+            addresses.join(",\n    "),
+            opcodes.join(",\n    ")
+        )
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    BEEP_FREQUENCY, BEEP_VOLUME, Palette, SAMPLE_RATE,
+    framebuffer::{Framebuffer, LORES},
+    pacing::FRAME_RATE,
+};
+use gif::{Encoder, Frame, Repeat};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{error::Error, fs::File, io::BufWriter, path::Path};
+
+/// Captures the display into an animated GIF at 60 fps and, optionally, the beeper into a WAV file.
+pub struct Recorder {
+    encoder: Encoder<BufWriter<File>>,
+    scale: u32,
+    frames: u64,
+    // Identical frames are merged into one with a longer delay.
+    pending: Option<Framebuffer>,
+    written_centis: u64,
+    audio: Option<WavWriter<BufWriter<File>>>,
+    samples: u64,
+    phase: f32,
+}
+
+impl Recorder {
+    /// Starts a recording at `path`. With `audio`, the beeper is written next to it as a `.wav` file.
+    pub fn new(
+        path: &Path,
+        palette: Palette,
+        scale: u32,
+        audio: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let file = BufWriter::new(File::create(path)?);
+        let global_palette = [
+            palette.background.r,
+            palette.background.g,
+            palette.background.b,
+            palette.foreground.r,
+            palette.foreground.g,
+            palette.foreground.b,
+        ];
+        let mut encoder = Encoder::new(
+            file,
+            (LORES.0 as u32 * scale) as u16,
+            (LORES.1 as u32 * scale) as u16,
+            &global_palette,
+        )?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let audio = if audio {
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate: SAMPLE_RATE as u32,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            };
+            Some(WavWriter::create(path.with_extension("wav"), spec)?)
+        } else {
+            None
+        };
+
+        Ok(Recorder {
+            encoder,
+            scale,
+            frames: 0,
+            pending: None,
+            written_centis: 0,
+            audio,
+            samples: 0,
+            phase: 0.0,
+        })
+    }
+
+    /// Adds one 1/60 s frame showing `display`, with the beeper on or off for its duration.
+    pub fn capture(&mut self, display: &Framebuffer, beeping: bool) -> Result<(), Box<dyn Error>> {
+        if self.pending.as_ref() != Some(display) {
+            self.flush()?;
+            self.pending = Some(*display);
+        }
+        self.frames += 1;
+
+        if let Some(audio) = &mut self.audio {
+            let target = self.frames * SAMPLE_RATE as u64 / u64::from(FRAME_RATE);
+            let phase_inc = BEEP_FREQUENCY / SAMPLE_RATE as f32;
+            while self.samples < target {
+                let sample = if !beeping {
+                    0.0
+                } else if self.phase <= 0.5 {
+                    BEEP_VOLUME
+                } else {
+                    -BEEP_VOLUME
+                };
+                audio.write_sample((sample * i16::MAX as f32) as i16)?;
+                self.phase = (self.phase + phase_inc) % 1.0;
+                self.samples += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the remaining frame and closes both files.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.flush()?;
+        self.encoder.into_inner()?;
+        if let Some(audio) = self.audio {
+            audio.finalize()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(display) = self.pending.take() else {
+            return Ok(());
+        };
+
+        // GIF delays are in 1/100 s, so round against the total elapsed time to avoid drift.
+        let elapsed_centis = self.frames * 100 / u64::from(FRAME_RATE);
+        let delay = elapsed_centis.saturating_sub(self.written_centis).max(1);
+        self.written_centis += delay;
+
+        let width = display.width() * self.scale as usize;
+        let height = display.height() * self.scale as usize;
+        let mut buffer = Vec::with_capacity(width * height);
+        for row in display.rows() {
+            let line: Vec<u8> = row
+                .flat_map(|pixel| std::iter::repeat_n(pixel as u8, self.scale as usize))
+                .collect();
+            for _ in 0..self.scale {
+                buffer.extend_from_slice(&line);
+            }
+        }
+
+        let frame = Frame {
+            width: width as u16,
+            height: height as u16,
+            delay: delay.min(u16::MAX as u64) as u16,
+            buffer: buffer.into(),
+            ..Frame::default()
+        };
+        self.encoder.write_frame(&frame)?;
+        Ok(())
+    }
+}
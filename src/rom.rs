@@ -0,0 +1,268 @@
+use crate::platform::Platform;
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// The CHIP-8 variants a ROM can be written for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RomFormat {
+    /// `.ch8`, the original COSMAC VIP interpreter.
+    Chip8,
+    /// `.sc8`, SUPER-CHIP.
+    SuperChip,
+    /// `.xo8`, XO-CHIP with 64 KiB of memory.
+    XoChip,
+    /// `.c8x`, CHIP-8X, whose programs start at 0x300.
+    Chip8X,
+}
+
+impl RomFormat {
+    /// The format belonging to a file extension, if it is a known one.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "ch8" | "c8" => Some(RomFormat::Chip8),
+            "sc8" => Some(RomFormat::SuperChip),
+            "xo8" => Some(RomFormat::XoChip),
+            "c8x" => Some(RomFormat::Chip8X),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from opcodes that only the extensions use, or from a size that only
+    /// fits into XO-CHIP's memory. Sprite data between the instructions can look like such
+    /// opcodes, so a known extension takes precedence.
+    pub fn detect(program: &[u8]) -> Self {
+        let chip_8 = Platform::CHIP_8;
+        if program.len() > chip_8.program_end - chip_8.load_address {
+            return RomFormat::XoChip;
+        }
+        let mut format = RomFormat::Chip8;
+        for opcode in program
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        {
+            match opcode {
+                // Long I, audio pattern, plane selection and register range save/load
+                0xF000 | 0xF002 => return RomFormat::XoChip,
+                _ if opcode & 0xF0FF == 0xF001 => return RomFormat::XoChip,
+                _ if matches!(opcode & 0xF00F, 0x5002 | 0x5003) => return RomFormat::XoChip,
+                // Scrolling, exit, resolution changes, large font and flag registers
+                0x00FB..=0x00FF => format = RomFormat::SuperChip,
+                _ if opcode & 0xFFF0 == 0x00C0 => format = RomFormat::SuperChip,
+                _ if matches!(opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085) => {
+                    format = RomFormat::SuperChip;
+                }
+                _ => (),
+            }
+        }
+        format
+    }
+}
+
+impl fmt::Display for RomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RomFormat::Chip8 => "CHIP-8",
+            RomFormat::SuperChip => "SUPER-CHIP",
+            RomFormat::XoChip => "XO-CHIP",
+            RomFormat::Chip8X => "CHIP-8X",
+        })
+    }
+}
+
+/// Why a ROM was rejected.
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Empty,
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+    /// An entry point that leaves no room for a whole instruction.
+    InvalidEntryPoint {
+        address: usize,
+    },
+    /// A font that would not fit between its address and the end of memory.
+    InvalidFontAddress {
+        address: usize,
+    },
+    /// A platform whose memory size is not a power of two from 4 KiB to 64 KiB.
+    InvalidMemorySize {
+        size: usize,
+    },
+    /// An in-memory stack that would reach into program memory.
+    StackTooDeep {
+        depth: usize,
+        max: usize,
+    },
+    /// A word in a hex text dump that is not a byte, on the given line.
+    InvalidHex {
+        line: usize,
+        word: String,
+    },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(error) => write!(f, "Could not read the ROM: {error}"),
+            RomError::Empty => write!(f, "The ROM is empty"),
+            RomError::TooLarge { size, max } => {
+                write!(f, "The ROM is {size} bytes, but at most {max} bytes fit")
+            }
+            RomError::InvalidEntryPoint { address } => {
+                write!(f, "The entry point {address:#05X} is outside of memory")
+            }
+            RomError::InvalidFontAddress { address } => {
+                write!(f, "The font at {address:#05X} does not fit into memory")
+            }
+            RomError::InvalidMemorySize { size } => {
+                write!(f, "Unsupported memory size of {size} bytes")
+            }
+            RomError::StackTooDeep { depth, max } => write!(
+                f,
+                "A stack of {depth} levels overlaps program memory; at most {max} fit"
+            ),
+            RomError::InvalidHex { line, word } => {
+                write!(f, "Invalid byte {word:?} in the hex dump on line {line}")
+            }
+        }
+    }
+}
+
+impl Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(error: io::Error) -> Self {
+        RomError::Io(error)
+    }
+}
+
+/// A validated program and the format it was written for.
+pub struct Rom {
+    pub format: RomFormat,
+    pub program: Vec<u8>,
+}
+
+impl Rom {
+    /// Reads and validates the ROM at `path`, see [`Rom::parse`].
+    pub fn load(path: &Path) -> Result<Self, RomError> {
+        let data = fs::read(path)?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        Rom::parse(data, extension)
+    }
+
+    /// Validates a ROM file's contents. Octo hex text dumps are converted to binary. The format
+    /// follows the extension if it is a known one, and is detected from the program otherwise.
+    /// Whether the program fits into memory depends on the platform, see
+    /// [`Platform::check_program`](crate::platform::Platform::check_program).
+    pub fn parse(data: Vec<u8>, extension: Option<&str>) -> Result<Self, RomError> {
+        let program = match std::str::from_utf8(&data) {
+            Ok(text) if is_hex_dump(text) => parse_hex_dump(text)?,
+            _ => data,
+        };
+        if program.is_empty() {
+            return Err(RomError::Empty);
+        }
+
+        let format = extension
+            .and_then(RomFormat::from_extension)
+            .unwrap_or_else(|| RomFormat::detect(&program));
+        Ok(Rom { format, program })
+    }
+}
+
+/// Whether `text` only consists of hex bytes such as `0x00 0xE0`, `00 E0` or `00E0`.
+fn is_hex_dump(text: &str) -> bool {
+    !text.trim().is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c.is_ascii_whitespace() || c == ',' || c == 'x')
+}
+
+fn parse_hex_dump(text: &str) -> Result<Vec<u8>, RomError> {
+    let mut program = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for word in line.split(|c: char| c.is_ascii_whitespace() || c == ',') {
+            let digits = word.strip_prefix("0x").unwrap_or(word);
+            let invalid = || RomError::InvalidHex {
+                line: index + 1,
+                word: word.to_string(),
+            };
+            if digits.len() % 2 != 0 {
+                return Err(invalid());
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+                program.push(u8::from_str_radix(pair, 16).map_err(|_| invalid())?);
+            }
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_dumps_are_recognised() {
+        assert!(is_hex_dump("0x00 0xE0\n0x12 0x00\n"));
+        assert!(is_hex_dump("00E0, 1200"));
+        assert!(!is_hex_dump("  \n"));
+        assert!(!is_hex_dump("; Octo source\n: main"));
+    }
+
+    #[test]
+    fn hex_dumps_are_parsed() {
+        let program = parse_hex_dump("0x00 0xE0\n00,e0 1200\n\n").unwrap();
+        assert_eq!(program, [0x00, 0xE0, 0x00, 0xE0, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn invalid_hex_words_are_reported_with_their_line() {
+        match parse_hex_dump("00E0\n0x0 12") {
+            Err(RomError::InvalidHex { line, word }) => {
+                assert_eq!((line, word.as_str()), (2, "0x0"))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(
+            parse_hex_dump("0xx0"),
+            Err(RomError::InvalidHex { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn formats_are_detected_from_opcodes() {
+        assert_eq!(
+            RomFormat::detect(&[0x00, 0xE0, 0x12, 0x00]),
+            RomFormat::Chip8
+        );
+        assert_eq!(
+            RomFormat::detect(&[0x00, 0xFF, 0x12, 0x00]),
+            RomFormat::SuperChip
+        );
+        assert_eq!(
+            RomFormat::detect(&[0x00, 0xC4, 0xF2, 0x30]),
+            RomFormat::SuperChip
+        );
+        assert_eq!(
+            RomFormat::detect(&[0x00, 0xFF, 0xF0, 0x00]),
+            RomFormat::XoChip
+        );
+        assert_eq!(RomFormat::detect(&[0x51, 0x22]), RomFormat::XoChip);
+        assert_eq!(RomFormat::detect(&[0; 0x1000]), RomFormat::XoChip);
+    }
+
+    #[test]
+    fn extensions_take_precedence_over_detection() {
+        let rom = Rom::parse(vec![0x00, 0xFF], Some("CH8")).unwrap();
+        assert_eq!(rom.format, RomFormat::Chip8);
+        let rom = Rom::parse(b"0x00 0xFF".to_vec(), None).unwrap();
+        assert_eq!(
+            (rom.format, rom.program),
+            (RomFormat::SuperChip, vec![0x00, 0xFF])
+        );
+        assert!(matches!(Rom::parse(Vec::new(), None), Err(RomError::Empty)));
+    }
+}
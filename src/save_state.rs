@@ -0,0 +1,131 @@
+use crate::{Chip8Context, framebuffer::LORES};
+use std::error::Error;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 2;
+const MAX_STACK_DEPTH: usize = 16;
+
+/// Size in bytes of every save state, not counting memory, see [`Chip8Context::save_state_size`].
+const FIXED_SIZE: usize = MAGIC.len()
+    + 1 // version
+    + LORES.0 * LORES.1 / 8 // display, one bit per pixel
+    + 2 // program counter
+    + 2 // index register
+    + 16 // registers
+    + 4 // delay timer
+    + 4 // sound timer
+    + 1 // stack depth
+    + MAX_STACK_DEPTH * 2
+    + 8; // cycles
+
+impl Chip8Context {
+    /// Size in bytes of every save state of this machine, which includes all of memory.
+    pub fn save_state_size(&self) -> usize {
+        FIXED_SIZE + self.memory.len()
+    }
+
+    /// Serializes the machine state. Fails if the stack is deeper than 16 entries.
+    pub fn save_state(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if self.stack.len() > MAX_STACK_DEPTH {
+            return Err("Stack too deep for a save state".into());
+        }
+
+        let mut state = Vec::with_capacity(self.save_state_size());
+        state.extend_from_slice(MAGIC);
+        state.push(VERSION);
+        state.extend_from_slice(&self.memory);
+        for y in 0..LORES.1 {
+            let pixels = (self.display.row(y) >> 64) as u64;
+            state.extend_from_slice(&pixels.to_be_bytes());
+        }
+        state.extend_from_slice(&(self.program_counter as u16).to_le_bytes());
+        state.extend_from_slice(&self.i.to_le_bytes());
+        state.extend_from_slice(&self.register);
+        state.extend_from_slice(&self.delay_timer.time.to_le_bytes());
+        state.extend_from_slice(&self.sound_timer.time.to_le_bytes());
+        state.push(self.stack.len() as u8);
+        for slot in 0..MAX_STACK_DEPTH {
+            let address = self.stack.get(slot).copied().unwrap_or(0) as u16;
+            state.extend_from_slice(&address.to_le_bytes());
+        }
+        state.extend_from_slice(&self.cycles.to_le_bytes());
+
+        Ok(state)
+    }
+
+    /// Restores a state written by [`Chip8Context::save_state`]. Fails without changing anything
+    /// if the state is invalid, e.g. with the program counter outside of memory.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Box<dyn Error>> {
+        if state.len() != self.save_state_size() || &state[..4] != MAGIC || state[4] != VERSION {
+            return Err("Invalid save state".into());
+        }
+        let mut reader = Reader { state, position: 5 };
+
+        let memory = reader.take(self.memory.len());
+        let rows = (0..LORES.1)
+            .map(|_| reader.take(8).try_into().map(u64::from_be_bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let program_counter = reader.u16() as usize;
+        let i = reader.u16();
+        let register = reader.take(16);
+        let delay = reader.u32();
+        let sound = reader.u32();
+        let depth = reader.take(1)[0] as usize;
+        let mut stack: Vec<usize> = (0..MAX_STACK_DEPTH)
+            .map(|_| reader.u16() as usize)
+            .collect();
+        let cycles = u64::from_le_bytes(reader.take(8).try_into()?);
+
+        // A corrupt state must not leave the machine fetching or writing outside of memory.
+        let mask = self.address_mask();
+        if program_counter > mask - 1 || i as usize > mask {
+            return Err("Invalid save state: PC or I outside of memory".into());
+        }
+        if depth > MAX_STACK_DEPTH.min(self.platform.stack.depth()) {
+            return Err("Invalid save state: stack too deep".into());
+        }
+        stack.truncate(depth);
+        if stack.iter().any(|&address| address > mask - 1) {
+            return Err("Invalid save state: return address outside of memory".into());
+        }
+
+        self.memory.copy_from_slice(memory);
+        self.invalidate_instruction_cache();
+        self.key_wait = None;
+        for (y, pixels) in rows.into_iter().enumerate() {
+            self.display.set_row(y, (pixels as u128) << 64);
+        }
+        self.program_counter = program_counter;
+        self.i = i;
+        self.register.copy_from_slice(register);
+        self.delay_timer.time = delay;
+        self.sound_timer.time = sound;
+        self.stack = stack;
+        self.cycles = cycles;
+        self.frame_instructions = 0;
+
+        self.redraw();
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    state: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.state[self.position..self.position + len];
+        self.position += len;
+        bytes
+    }
+    fn u16(&mut self) -> u16 {
+        let bytes = self.take(2);
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+    fn u32(&mut self) -> u32 {
+        let bytes = self.take(4);
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{Palette, framebuffer::Framebuffer};
+use std::{
+    error::Error,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Writes `display` to `path` as an RGB PNG, scaling every pixel to a `scale`x`scale` block.
+pub fn save_png(
+    display: &Framebuffer,
+    palette: Palette,
+    scale: u32,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let width = display.width() as u32 * scale;
+    let height = display.height() as u32 * scale;
+
+    let mut data = Vec::with_capacity((width * height * 3) as usize);
+    for row in display.rows() {
+        let mut line = Vec::with_capacity((width * 3) as usize);
+        for pixel in row {
+            let color = if pixel {
+                palette.foreground
+            } else {
+                palette.background
+            };
+            for _ in 0..scale {
+                line.extend_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Returns a path like `chip8-1712345678901.png` in the current directory.
+pub fn timestamped_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    PathBuf::from(format!("chip8-{millis}.png"))
+}
@@ -0,0 +1,284 @@
+use crate::{Chip8Context, screenshot};
+use rhai::{AST, Dynamic, Engine, FnPtr, INT};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// A [Rhai](https://rhai.rs) script driving the emulator. The script's top level runs once when it
+/// is loaded and registers hooks, which receive closures or function names (`Fn("name")`):
+///
+/// ```text
+/// on_frame(|| ...)                        after every 60 Hz frame
+/// on_instruction(0x218, || ...)           before the instruction at 0x218 executes
+/// on_write(0x300, 0x30F, |address, value| ...)   after FX33/FX55 write into the range
+/// ```
+///
+/// The API available to the script and its hooks:
+///
+/// ```text
+/// read_memory(address)  write_memory(address, value)
+/// register(x)  set_register(x, value)  index()  set_index(value)  pc()  set_pc(address)
+/// press_key(key)  release_key(key)      keys stay pressed until released
+/// screenshot()  screenshot(path)  frame()
+/// ```
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    state: Rc<RefCell<State>>,
+}
+
+/// The machine as the script sees it while a hook runs, and the registered hooks.
+struct State {
+    memory: Vec<u8>,
+    address_mask: usize,
+    registers: [u8; 16],
+    index: u16,
+    program_counter: usize,
+    frame: u64,
+    held_keys: [bool; 16],
+    released_keys: Vec<u8>,
+    screenshots: Vec<PathBuf>,
+    frame_hooks: Vec<FnPtr>,
+    instruction_hooks: BTreeMap<usize, Vec<FnPtr>>,
+    write_hooks: Vec<(usize, usize, FnPtr)>,
+}
+
+impl Script {
+    fn new(path: &Path, context: &mut Chip8Context) -> Result<Self, Box<dyn Error>> {
+        let state = Rc::new(RefCell::new(State {
+            memory: vec![0; context.memory.len()],
+            address_mask: context.address_mask(),
+            registers: [0; 16],
+            index: 0,
+            program_counter: 0,
+            frame: 0,
+            held_keys: [false; 16],
+            released_keys: Vec::new(),
+            screenshots: Vec::new(),
+            frame_hooks: Vec::new(),
+            instruction_hooks: BTreeMap::new(),
+            write_hooks: Vec::new(),
+        }));
+        let mut engine = Engine::new();
+        register_api(&mut engine, &state);
+        let ast = engine.compile_file(path.to_path_buf())?;
+
+        let script = Script { engine, ast, state };
+        script.load(context);
+        script.engine.run_ast(&script.ast)?;
+        script.store(context)?;
+        Ok(script)
+    }
+
+    /// Presses the held keys and runs the hooks for the instruction about to execute.
+    pub(crate) fn before_instruction(
+        &mut self,
+        context: &mut Chip8Context,
+    ) -> Result<(), Box<dyn Error>> {
+        let hooks = {
+            let state = self.state.borrow();
+            for (key, &held) in state.held_keys.iter().enumerate() {
+                if held {
+                    context.set_key(key as u8, true);
+                }
+            }
+            match state.instruction_hooks.get(&context.program_counter) {
+                Some(hooks) => hooks.iter().map(|hook| (hook.clone(), vec![])).collect(),
+                None => return Ok(()),
+            }
+        };
+        self.run_hooks(context, hooks)
+    }
+
+    /// Runs the write hooks for the memory written by the last instruction.
+    pub(crate) fn after_instruction(
+        &mut self,
+        context: &mut Chip8Context,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some((start, len)) = context.last_write else {
+            return Ok(());
+        };
+        let hooks = self
+            .state
+            .borrow()
+            .write_hooks
+            .iter()
+            .flat_map(|(hook_start, hook_end, hook)| {
+                (start.max(*hook_start)..(start + len).min(*hook_end + 1).min(context.memory.len()))
+                    .map(|address| {
+                        let args = vec![
+                            Dynamic::from(address as INT),
+                            Dynamic::from(context.memory[address] as INT),
+                        ];
+                        (hook.clone(), args)
+                    })
+            })
+            .collect();
+        self.run_hooks(context, hooks)
+    }
+
+    pub(crate) fn end_frame(&mut self, context: &mut Chip8Context) -> Result<(), Box<dyn Error>> {
+        let hooks = {
+            let mut state = self.state.borrow_mut();
+            state.frame += 1;
+            state
+                .frame_hooks
+                .iter()
+                .map(|hook| (hook.clone(), vec![]))
+                .collect()
+        };
+        self.run_hooks(context, hooks)
+    }
+
+    fn run_hooks(
+        &mut self,
+        context: &mut Chip8Context,
+        hooks: Vec<(FnPtr, Vec<Dynamic>)>,
+    ) -> Result<(), Box<dyn Error>> {
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        self.load(context);
+        for (hook, args) in hooks {
+            // Whatever a hook returns is ignored.
+            let _ = hook.call::<Dynamic>(&self.engine, &self.ast, args)?;
+        }
+        self.store(context)
+    }
+
+    /// Copies the machine state into the script's view.
+    fn load(&self, context: &Chip8Context) {
+        let mut state = self.state.borrow_mut();
+        state.memory.copy_from_slice(&context.memory);
+        state.registers = context.register;
+        state.index = context.i;
+        state.program_counter = context.program_counter;
+    }
+
+    /// Applies the changes the script made to its view and takes the requested screenshots.
+    fn store(&self, context: &mut Chip8Context) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.borrow_mut();
+        for (address, &value) in state.memory.iter().enumerate() {
+            if context.memory[address] != value {
+                context.write_memory(address, value);
+            }
+        }
+        context.register = state.registers;
+        context.i = state.index;
+        context.program_counter = state.program_counter;
+        for key in state.released_keys.drain(..) {
+            context.set_key(key, false);
+        }
+        for path in state.screenshots.drain(..) {
+            context.save_screenshot(&path)?;
+            println!("Saved screenshot to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+fn register_api(engine: &mut Engine, state: &Rc<RefCell<State>>) {
+    let s = state.clone();
+    engine.register_fn("on_frame", move |hook: FnPtr| {
+        s.borrow_mut().frame_hooks.push(hook);
+    });
+    let s = state.clone();
+    engine.register_fn("on_instruction", move |address: INT, hook: FnPtr| {
+        let mut state = s.borrow_mut();
+        let address = address as usize & state.address_mask;
+        state
+            .instruction_hooks
+            .entry(address)
+            .or_default()
+            .push(hook);
+    });
+    let s = state.clone();
+    engine.register_fn("on_write", move |start: INT, end: INT, hook: FnPtr| {
+        let mut state = s.borrow_mut();
+        let mask = state.address_mask;
+        state
+            .write_hooks
+            .push((start as usize & mask, end as usize & mask, hook));
+    });
+
+    let s = state.clone();
+    engine.register_fn("read_memory", move |address: INT| -> INT {
+        let state = s.borrow();
+        state.memory[address as usize & state.address_mask] as INT
+    });
+    let s = state.clone();
+    engine.register_fn("write_memory", move |address: INT, value: INT| {
+        let mut state = s.borrow_mut();
+        let address = address as usize & state.address_mask;
+        state.memory[address] = value as u8;
+    });
+    let s = state.clone();
+    engine.register_fn("register", move |x: INT| -> INT {
+        s.borrow().registers[x as usize & 0xF] as INT
+    });
+    let s = state.clone();
+    engine.register_fn("set_register", move |x: INT, value: INT| {
+        s.borrow_mut().registers[x as usize & 0xF] = value as u8;
+    });
+    let s = state.clone();
+    engine.register_fn("index", move || -> INT { s.borrow().index as INT });
+    let s = state.clone();
+    engine.register_fn("set_index", move |value: INT| {
+        let mut state = s.borrow_mut();
+        state.index = (value as usize & state.address_mask) as u16;
+    });
+    let s = state.clone();
+    engine.register_fn("pc", move || -> INT { s.borrow().program_counter as INT });
+    let s = state.clone();
+    engine.register_fn("set_pc", move |address: INT| {
+        let mut state = s.borrow_mut();
+        state.program_counter = address as usize & state.address_mask;
+    });
+    let s = state.clone();
+    engine.register_fn("press_key", move |key: INT| {
+        s.borrow_mut().held_keys[key as usize & 0xF] = true;
+    });
+    let s = state.clone();
+    engine.register_fn("release_key", move |key: INT| {
+        let mut state = s.borrow_mut();
+        state.held_keys[key as usize & 0xF] = false;
+        state.released_keys.push(key as u8 & 0xF);
+    });
+    let s = state.clone();
+    engine.register_fn("screenshot", move || {
+        s.borrow_mut()
+            .screenshots
+            .push(screenshot::timestamped_path());
+    });
+    let s = state.clone();
+    engine.register_fn("screenshot", move |path: &str| {
+        s.borrow_mut().screenshots.push(PathBuf::from(path));
+    });
+    let s = state.clone();
+    engine.register_fn("frame", move || -> INT { s.borrow().frame as INT });
+}
+
+type ScriptHook = fn(&mut Script, &mut Chip8Context) -> Result<(), Box<dyn Error>>;
+
+impl Chip8Context {
+    /// Loads a script, runs its top level and installs its hooks, see [`Script`].
+    pub fn load_script(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.script = None;
+        let script = Script::new(path, self)?;
+        self.script = Some(Box::new(script));
+        Ok(())
+    }
+
+    pub(crate) fn run_script(&mut self, hook: ScriptHook) -> Result<(), Box<dyn Error>> {
+        let Some(mut script) = self.script.take() else {
+            return Ok(());
+        };
+        let result = hook(&mut script, self);
+        self.script = Some(script);
+        result
+    }
+}
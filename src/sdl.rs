@@ -0,0 +1,158 @@
+use crate::{
+    BEEP_FREQUENCY, BEEP_VOLUME, Color, LOGICAL_WINDOW_SIZE, PALETTE, SAMPLE_RATE,
+    framebuffer::Framebuffer,
+    frontend::{AudioSink, KeyboardKeypad, VideoSink},
+};
+use sdl2::{
+    AudioSubsystem,
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+    event::Event,
+    keyboard::Scancode,
+    pixels,
+    rect::Point,
+    render::WindowCanvas,
+    video::Window,
+};
+use std::error::Error;
+
+/// Draws the display in an SDL window.
+pub struct Renderer {
+    canvas: WindowCanvas,
+    vsync: bool,
+}
+
+impl Renderer {
+    /// With `vsync`, presenting waits for the display's vertical blank.
+    pub fn new(window: Window, vsync: bool) -> Result<Self, Box<dyn Error>> {
+        let mut canvas = window.into_canvas().accelerated();
+        if vsync {
+            canvas = canvas.present_vsync();
+        }
+        let mut canvas = canvas.build()?;
+        canvas.set_logical_size(LOGICAL_WINDOW_SIZE.0, LOGICAL_WINDOW_SIZE.1)?;
+
+        Ok(Renderer { canvas, vsync })
+    }
+}
+
+impl VideoSink for Renderer {
+    /// Unchanged frames are skipped, except with vsync where presenting paces the loop.
+    fn present(&mut self, display: &Framebuffer, changed: bool) -> Result<(), Box<dyn Error>> {
+        if !changed && !self.vsync {
+            return Ok(());
+        }
+        let points: Vec<Point> = display
+            .lit_pixels()
+            .map(|(x, y)| Point::new(x as i32, y as i32))
+            .collect();
+        self.canvas.set_draw_color(PALETTE.background);
+        self.canvas.clear();
+        self.canvas.set_draw_color(PALETTE.foreground);
+        self.canvas.draw_points(points.as_slice())?;
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn set_title(&mut self, title: &str) -> Result<(), Box<dyn Error>> {
+        self.canvas.window_mut().set_title(title)?;
+        Ok(())
+    }
+}
+
+/// Plays the beeper as a square wave on the default audio device.
+pub struct Beeper {
+    device: AudioDevice<SquareWave>,
+    playing: bool,
+}
+
+impl Beeper {
+    pub fn new(audio: &AudioSubsystem) -> Result<Self, Box<dyn Error>> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio.open_playback(None, &desired_spec, |spec| SquareWave {
+            phase_inc: BEEP_FREQUENCY / spec.freq as f32,
+            phase: 0.0,
+            volme: BEEP_VOLUME,
+        })?;
+
+        Ok(Beeper {
+            device,
+            playing: false,
+        })
+    }
+}
+
+impl AudioSink for Beeper {
+    fn set_beeping(&mut self, beeping: bool) -> Result<(), Box<dyn Error>> {
+        if beeping != self.playing {
+            self.playing = beeping;
+            if beeping {
+                self.device.resume();
+            } else {
+                self.device.pause();
+            }
+        }
+        Ok(())
+    }
+}
+
+struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    volme: f32,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        for x in out.iter_mut() {
+            *x = if self.phase <= 0.5 {
+                self.volme
+            } else {
+                -self.volme
+            };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+/// The label of a letter or digit key by its position on a QWERTY keyboard.
+const fn key_label(scancode: Scancode) -> Option<char> {
+    // SDL numbers the letter keys A to Z and then the digit keys 1 to 9 and 0 consecutively.
+    let code = scancode as i32;
+    match code {
+        0x04..=0x1D => Some((b'a' + (code - 0x04) as u8) as char),
+        0x1E..=0x26 => Some((b'1' + (code - 0x1E) as u8) as char),
+        0x27 => Some('0'),
+        _ => None,
+    }
+}
+
+/// Passes a keyboard event to `keyboard` and returns the keypad key it changed and whether that
+/// key is now down. Key repeats are ignored.
+pub fn keypad_event(event: &Event, keyboard: &mut KeyboardKeypad) -> Option<(u8, bool)> {
+    let (scancode, pressed) = match *event {
+        Event::KeyDown {
+            scancode: Some(scancode),
+            repeat: false,
+            ..
+        } => (scancode, true),
+        Event::KeyUp {
+            scancode: Some(scancode),
+            ..
+        } => (scancode, false),
+        _ => return None,
+    };
+    keyboard.key_event(key_label(scancode)?, pressed)
+}
+
+impl From<Color> for pixels::Color {
+    fn from(color: Color) -> Self {
+        pixels::Color::RGB(color.r, color.g, color.b)
+    }
+}
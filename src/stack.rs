@@ -0,0 +1,130 @@
+use crate::{Chip8Context, instruction::read_opcode, watch::Access};
+use std::{error::Error, fmt};
+
+/// Where subroutine return addresses are kept.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StackModel {
+    /// Outside of emulated memory, where programs cannot see it.
+    Host { depth: usize },
+    /// In emulated memory, growing down from `top` (exclusive) with every address stored big
+    /// endian, as the COSMAC VIP interpreter did. Programs can read and overwrite it.
+    Memory { top: usize, depth: usize },
+}
+
+impl StackModel {
+    /// The number of nested calls before [`StackError::Overflow`].
+    pub const fn depth(self) -> usize {
+        match self {
+            StackModel::Host { depth } | StackModel::Memory { depth, .. } => depth,
+        }
+    }
+
+    /// The deepest stack that stays out of program memory, which ends at `program_end`.
+    pub const fn max_depth(self, program_end: usize) -> usize {
+        match self {
+            StackModel::Host { .. } => usize::MAX,
+            StackModel::Memory { top, .. } => top.saturating_sub(program_end) / 2,
+        }
+    }
+
+    pub const fn with_depth(self, depth: usize) -> Self {
+        match self {
+            StackModel::Host { .. } => StackModel::Host { depth },
+            StackModel::Memory { top, .. } => StackModel::Memory { top, depth },
+        }
+    }
+
+    /// The memory address of stack slot `slot`, counted from the bottom, before it is masked
+    /// into memory.
+    const fn slot_address(top: usize, slot: usize) -> usize {
+        top.wrapping_sub(2 * (slot + 1))
+    }
+}
+
+/// Why a `2NNN` call or `00EE` return could not be executed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StackError {
+    /// A call with `depth` return addresses already on the stack.
+    Overflow { address: usize, depth: usize },
+    /// A return with an empty stack.
+    Underflow { address: usize },
+}
+
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackError::Overflow { address, depth } => write!(
+                f,
+                "Stack overflow at {address:#05X}: more than {depth} nested calls"
+            ),
+            StackError::Underflow { address } => {
+                write!(
+                    f,
+                    "Stack underflow at {address:#05X}: return without a call"
+                )
+            }
+        }
+    }
+}
+
+impl Error for StackError {}
+
+impl Chip8Context {
+    /// Pushes the return address of a call made from `address`.
+    pub(crate) fn push_return_address(&mut self, address: usize) -> Result<(), StackError> {
+        let depth = self.stack.len();
+        if depth >= self.platform.stack.depth() {
+            return Err(StackError::Overflow { address, depth });
+        }
+        if let StackModel::Memory { top, .. } = self.platform.stack {
+            let mask = self.address_mask();
+            let slot = StackModel::slot_address(top, depth) & mask;
+            let bytes = (self.program_counter as u16).to_be_bytes();
+            self.access_memory(slot, 2, Access::Write);
+            self.memory[slot] = bytes[0];
+            self.memory[(slot + 1) & mask] = bytes[1];
+        }
+        self.stack.push(self.program_counter);
+        Ok(())
+    }
+
+    /// Pops the return address for a return made from `address`.
+    pub(crate) fn pop_return_address(&mut self, address: usize) -> Result<usize, StackError> {
+        let returning = self.stack.pop().ok_or(StackError::Underflow { address })?;
+        if let StackModel::Memory { top, .. } = self.platform.stack {
+            let slot = StackModel::slot_address(top, self.stack.len()) & self.address_mask();
+            self.access_memory(slot, 2, Access::Read);
+        }
+        Ok(returning)
+    }
+
+    /// Rereads the return addresses after the `len` bytes of memory at `start` were written, if
+    /// the stack is kept there.
+    pub(crate) fn load_memory_stack(&mut self, start: usize, len: usize) {
+        let StackModel::Memory { top, .. } = self.platform.stack else {
+            return;
+        };
+        let bottom = top.saturating_sub(2 * self.stack.len());
+        if start + len > bottom && start < top {
+            self.read_memory_stack(top);
+        }
+    }
+
+    /// Changes the number of return addresses on the stack, e.g. from a debugger, up to the
+    /// platform's depth. An in-memory stack takes the new return addresses from memory, where they
+    /// already are; other stacks get zeros.
+    pub(crate) fn set_stack_depth(&mut self, depth: usize) {
+        self.stack.resize(depth.min(self.platform.stack.depth()), 0);
+        if let StackModel::Memory { top, .. } = self.platform.stack {
+            self.read_memory_stack(top);
+        }
+    }
+
+    fn read_memory_stack(&mut self, top: usize) {
+        let mask = self.address_mask();
+        for slot in 0..self.stack.len() {
+            let address = StackModel::slot_address(top, slot) & mask;
+            self.stack[slot] = read_opcode(&self.memory, address) as usize & mask;
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    framebuffer::Framebuffer,
+    frontend::{AudioSink, InputSource, KeyboardKeypad, VideoSink},
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    error::Error,
+    io::{Stdout, Write, stdout},
+    time::{Duration, Instant},
+};
+
+/// How long a key counts as held after a press when the terminal cannot report releases. The
+/// first press has to last until autorepeat starts, usually after 250-600 ms; repeats then follow
+/// each other much faster.
+const KEY_HOLD: Duration = Duration::from_millis(500);
+const KEY_REPEAT_HOLD: Duration = Duration::from_millis(150);
+
+#[derive(Clone, Copy)]
+enum KeyState {
+    Up,
+    Down,
+    // Terminals without release events only repeat presses, so releases are emulated.
+    DownUntil(Instant),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TerminalStyle {
+    /// One character per 1x2 pixels using `▀`, `▄` and `█`.
+    HalfBlock,
+    /// One character per 2x4 pixels using braille patterns.
+    Braille,
+}
+
+/// Reads the keypad from a terminal, e.g. over SSH, which is kept in raw mode on the alternate
+/// screen until dropped. The display is drawn by a [`TerminalScreen`].
+pub struct Terminal {
+    keyboard: KeyboardKeypad,
+    keys: [KeyState; 16],
+    release_events: bool,
+}
+
+impl Terminal {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let mut out = stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+
+        let release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if release_events {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Terminal {
+            keyboard: KeyboardKeypad::default(),
+            keys: [KeyState::Up; 16],
+            release_events,
+        })
+    }
+
+    /// Reads pending key events. Returns `false` once the user asked to quit.
+    pub fn poll_events(&mut self) -> Result<bool, Box<dyn Error>> {
+        let now = Instant::now();
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            if code == KeyCode::Esc
+                || (code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL))
+            {
+                return Ok(false);
+            }
+            let KeyCode::Char(c) = code else {
+                continue;
+            };
+            let pressed = kind != KeyEventKind::Release;
+            let Some((key, down)) = self.keyboard.key_event(c, pressed) else {
+                continue;
+            };
+            let state = &mut self.keys[key as usize];
+            *state = match *state {
+                _ if !down => KeyState::Up,
+                _ if self.release_events => KeyState::Down,
+                KeyState::DownUntil(deadline) if deadline > now => {
+                    KeyState::DownUntil(now + KEY_REPEAT_HOLD)
+                }
+                _ => KeyState::DownUntil(now + KEY_HOLD),
+            };
+        }
+        Ok(true)
+    }
+}
+
+impl InputSource for Terminal {
+    fn keypad(&mut self) -> [bool; 16] {
+        let now = Instant::now();
+        let mut keypad = [false; 16];
+        for (key, state) in self.keys.iter_mut().enumerate() {
+            if let KeyState::DownUntil(deadline) = *state
+                && deadline <= now
+            {
+                *state = KeyState::Up;
+            }
+            keypad[key] = !matches!(state, KeyState::Up);
+        }
+        keypad
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut out = stdout();
+        if self.release_events {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Draws the display in the terminal with text characters.
+pub struct TerminalScreen {
+    out: Stdout,
+    style: TerminalStyle,
+}
+
+impl TerminalScreen {
+    pub fn new(style: TerminalStyle) -> Self {
+        TerminalScreen {
+            out: stdout(),
+            style,
+        }
+    }
+}
+
+impl VideoSink for TerminalScreen {
+    fn present(&mut self, display: &Framebuffer, changed: bool) -> Result<(), Box<dyn Error>> {
+        if !changed {
+            return Ok(());
+        }
+        let lines = match self.style {
+            TerminalStyle::HalfBlock => half_block_lines(display),
+            TerminalStyle::Braille => braille_lines(display),
+        };
+        for (y, line) in lines.iter().enumerate() {
+            queue!(self.out, MoveTo(0, y as u16), Print(line))?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Rings the terminal bell when a beep starts.
+#[derive(Default)]
+pub struct TerminalBell {
+    was_beeping: bool,
+}
+
+impl AudioSink for TerminalBell {
+    fn set_beeping(&mut self, beeping: bool) -> Result<(), Box<dyn Error>> {
+        if beeping && !self.was_beeping {
+            execute!(stdout(), Print('\x07'))?;
+        }
+        self.was_beeping = beeping;
+        Ok(())
+    }
+}
+
+fn half_block_lines(display: &Framebuffer) -> Vec<String> {
+    (0..display.height())
+        .step_by(2)
+        .map(|y| {
+            (0..display.width())
+                .map(|x| match (display.pixel(x, y), display.pixel(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn braille_lines(display: &Framebuffer) -> Vec<String> {
+    // Dot bits of a braille cell, indexed by [row][column].
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    (0..display.height())
+        .step_by(4)
+        .map(|y| {
+            (0..display.width())
+                .step_by(2)
+                .map(|x| {
+                    let mut bits = 0;
+                    for (row, dots) in DOTS.iter().enumerate() {
+                        for (column, dot) in dots.iter().enumerate() {
+                            if display.pixel(x + column, y + row) {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap_or(' ')
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+use crate::{Chip8Context, disassembler::mnemonic, instruction::read_opcode};
+use std::{
+    error::Error,
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Writes one line per executed instruction, showing the state before it runs:
+///
+/// ```text
+/// cycle      PC  op   mnemonic         V0 V1 .. VF                                      I   SP DT ST
+/// 0000000042 21A 3400 SE V4, 0x00      00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I=050 SP=00 DT=1F ST=00
+/// ```
+///
+/// Every field has a fixed width, so traces from different runs can be compared with `diff`.
+pub struct Tracer {
+    writer: BufWriter<File>,
+}
+
+impl Tracer {
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Tracer {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub(crate) fn write_line(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        writeln!(self.writer, "{line}")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Chip8Context {
+    pub(crate) fn trace_line(&self) -> String {
+        // Addresses are as wide as the platform's largest one, so every line has the same layout.
+        let digits = self.address_digits();
+        let pc = self.program_counter;
+        let opcode = read_opcode(&self.memory, pc);
+        let mut line = format!(
+            "{:010} {pc:0digits$X} {opcode:04X} {:<16}",
+            self.cycles,
+            mnemonic(opcode)
+        );
+        for register in self.register {
+            let _ = write!(line, " {register:02X}");
+        }
+        let _ = write!(
+            line,
+            " I={:0digits$X} SP={:02X} DT={:02X} ST={:02X}",
+            self.i,
+            self.stack.len(),
+            self.delay_timer.time,
+            self.sound_timer.time
+        );
+        line
+    }
+}
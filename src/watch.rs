@@ -0,0 +1,136 @@
+use std::{fmt, str::FromStr};
+
+/// How an instruction touched memory.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// `DXYN` sprite data, `FX65` and returns with an in-memory stack.
+    Read,
+    /// `FX33`, `FX55` and calls with an in-memory stack.
+    Write,
+    /// The two bytes of an instruction being fetched.
+    Fetch,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Fetch => "fetch",
+        })
+    }
+}
+
+/// A register whose changes can be watched.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    /// Parses `I` or `V0`-`VF`, in either case.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let name = text.to_ascii_uppercase();
+        if name == "I" {
+            return Ok(Register::I);
+        }
+        match name.strip_prefix('V') {
+            Some(digit) if digit.len() == 1 => u8::from_str_radix(digit, 16)
+                .map(Register::V)
+                .map_err(|_| format!("Invalid register: {text}")),
+            _ => Err(format!("Invalid register: {text}")),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::I => f.write_str("I"),
+        }
+    }
+}
+
+/// A condition that stops execution after the instruction that met it.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Watchpoint {
+    /// An access of one of the enabled kinds to an address in `start..=end`.
+    Memory {
+        start: usize,
+        end: usize,
+        read: bool,
+        write: bool,
+        fetch: bool,
+    },
+    /// Any change of the register's value.
+    Register(Register),
+}
+
+impl Watchpoint {
+    /// The first address of `start..start + len` that is watched for `access`, if any.
+    pub(crate) fn matched_address(
+        &self,
+        start: usize,
+        len: usize,
+        access: Access,
+    ) -> Option<usize> {
+        let Watchpoint::Memory {
+            start: watch_start,
+            end: watch_end,
+            read,
+            write,
+            fetch,
+        } = *self
+        else {
+            return None;
+        };
+        let enabled = match access {
+            Access::Read => read,
+            Access::Write => write,
+            Access::Fetch => fetch,
+        };
+        let first = start.max(watch_start);
+        (enabled && len > 0 && first <= watch_end && first < start + len).then_some(first)
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Watchpoint::Memory {
+                start,
+                end,
+                read,
+                write,
+                fetch,
+            } => {
+                let kinds: Vec<&str> = [(read, "read"), (write, "write"), (fetch, "fetch")]
+                    .into_iter()
+                    .filter(|&(enabled, _)| enabled)
+                    .map(|(_, kind)| kind)
+                    .collect();
+                write!(f, "{} {start:#05X}", kinds.join("/"))?;
+                if end != start {
+                    write!(f, "-{end:#05X}")?;
+                }
+                Ok(())
+            }
+            Watchpoint::Register(register) => write!(f, "{register} changes"),
+        }
+    }
+}
+
+/// A triggered watchpoint together with the instruction that triggered it.
+pub struct WatchHit {
+    /// Index into [`Chip8Context::watchpoints`](crate::Chip8Context::watchpoints).
+    pub watchpoint: usize,
+    /// Address of the triggering instruction.
+    pub address: usize,
+    pub opcode: u16,
+    /// What happened, e.g. `write 0x302` or `VF 00 -> 01`.
+    pub detail: String,
+}
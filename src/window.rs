@@ -0,0 +1,314 @@
+use crate::{Options, finish, start};
+use chip_8::{
+    Chip8Context,
+    debugger::Debugger,
+    frontend::KeyboardKeypad,
+    gdb::GdbStub,
+    memory_viewer::MemoryViewer,
+    pacing::{FRAME_DURATION, FramePacer},
+    platform::Platform,
+    screenshot,
+    sdl::{Beeper, Renderer, keypad_event},
+};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+};
+use std::{error::Error, thread::sleep, time::Instant};
+
+/// Speeds selectable with `-` and `=`, relative to real time. `None` runs uncapped.
+const SPEEDS: [Option<f64>; 6] = [Some(0.25), Some(0.5), Some(1.0), Some(2.0), Some(4.0), None];
+const NORMAL_SPEED: usize = 2;
+
+/// Runs `file` in an SDL window with sound, the memory viewer, the cheat menu and the debuggers.
+pub fn run_window(
+    file: Vec<u8>,
+    platform: Platform,
+    options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let audio_subsystem = sdl_context.audio()?;
+    let window = video_subsystem
+        .window("CHIP-8", chip_8::WINDOW_SIZE.0, chip_8::WINDOW_SIZE.1)
+        .position_centered()
+        .vulkan()
+        .build()?;
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let main_window = window.id();
+    let renderer = Renderer::new(window, options.vsync)?;
+    let beeper = Beeper::new(&audio_subsystem)?;
+    let mut chip_8_context = Chip8Context::builder(file)
+        .platform(platform)
+        .video(renderer)
+        .audio(beeper)
+        .build()?;
+    start(&mut chip_8_context, options)?;
+
+    let mut memory_viewer: Option<MemoryViewer> = None;
+    let mut keyboard = KeyboardKeypad::default();
+    let mut paused = false;
+    let mut speed = NORMAL_SPEED;
+    // Instructions and frames to run while paused, queued by instruction and frame advance.
+    let mut steps = 0;
+    let mut frame_steps = 0;
+    let mut title = String::new();
+    let mut pacer = FramePacer::new();
+    // While the cheat menu is open the emulator is paused; this is the state to return to.
+    let mut cheat_menu: Option<bool> = None;
+    let mut debugger = options.debug.then(|| Debugger::new(options.cheat_file()));
+    let mut gdb_stub = options.gdb_port.map(GdbStub::new).transpose()?;
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } if cheat_menu.is_some() => match keycode {
+                    Keycode::F3 | Keycode::ESCAPE => {
+                        paused = cheat_menu.take().unwrap_or_default();
+                        println!("Closed the cheat menu");
+                    }
+                    _ => {
+                        let digit = keycode.name().parse::<usize>().ok();
+                        let index = digit.map(|digit| (digit + 9) % 10);
+                        if let Some(index) = index
+                            && chip_8_context.toggle_cheat(index).is_some()
+                        {
+                            print_cheat_menu(&chip_8_context);
+                        }
+                    }
+                },
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::ESCAPE),
+                    ..
+                } => break 'running,
+                Event::Window {
+                    win_event: WindowEvent::Close,
+                    window_id,
+                    ..
+                } => {
+                    if memory_viewer
+                        .as_ref()
+                        .is_some_and(|viewer| viewer.window_id() == window_id)
+                    {
+                        memory_viewer = None;
+                    } else {
+                        break 'running;
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } => paused = !paused,
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } => {
+                    paused = true;
+                    frame_steps += 1;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::M),
+                    ..
+                } => {
+                    paused = true;
+                    steps += 1;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::EQUALS | Keycode::KP_PLUS),
+                    ..
+                } => speed = (speed + 1).min(SPEEDS.len() - 1),
+                Event::KeyDown {
+                    keycode: Some(Keycode::MINUS | Keycode::KP_MINUS),
+                    ..
+                } => speed = speed.saturating_sub(1),
+                Event::KeyDown {
+                    keycode: Some(Keycode::BACKSPACE),
+                    ..
+                } => speed = NORMAL_SPEED,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => {
+                    memory_viewer = match memory_viewer {
+                        Some(_) => None,
+                        None => Some(MemoryViewer::new(&video_subsystem, &chip_8_context)?),
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => {
+                    cheat_menu = Some(paused);
+                    paused = true;
+                    print_cheat_menu(&chip_8_context);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => {
+                    let path = screenshot::timestamped_path();
+                    chip_8_context.save_screenshot(&path)?;
+                    println!("Saved screenshot to {}", path.display());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => {
+                    if chip_8_context.is_recording() {
+                        chip_8_context.stop_recording()?;
+                        println!("Stopped recording");
+                    } else {
+                        let path = screenshot::timestamped_path().with_extension("gif");
+                        chip_8_context.start_recording(&path, options.record_audio)?;
+                        println!("Recording to {}", path.display());
+                    }
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => {
+                    // Key releases are not reported to unfocused windows.
+                    keyboard.release_all();
+                    for key in 0..16 {
+                        chip_8_context.set_key(key, false);
+                    }
+                }
+                event => {
+                    if event.get_window_id() == Some(main_window)
+                        && let Some((key, down)) = keypad_event(&event, &mut keyboard)
+                    {
+                        chip_8_context.set_key(key, down);
+                    }
+                    if let Some(viewer) = &mut memory_viewer {
+                        viewer.handle_event(&event, &mut chip_8_context, paused);
+                    }
+                }
+            }
+        }
+
+        if let Some(debugger) = &mut debugger {
+            debugger.poll(&mut chip_8_context, &mut paused);
+        }
+        if let Some(gdb_stub) = &mut gdb_stub
+            && !gdb_stub.poll(&mut chip_8_context, &mut paused)?
+        {
+            break 'running;
+        }
+        let mut run = |chip_8_context: &mut Chip8Context, paused: &mut bool, instructions| {
+            run_instructions(
+                chip_8_context,
+                instructions,
+                debugger.as_ref(),
+                gdb_stub.as_mut(),
+                paused,
+            )
+        };
+        if paused {
+            pacer.reset();
+            run(&mut chip_8_context, &mut paused, std::mem::take(&mut steps))?;
+            // A frame advance runs to the next frame boundary, even after single steps.
+            for _ in 0..std::mem::take(&mut frame_steps) {
+                let instructions = chip_8_context.instructions_left_in_frame();
+                run(&mut chip_8_context, &mut paused, instructions)?;
+            }
+        } else if let Some(multiplier) = SPEEDS[speed] {
+            for _ in 0..pacer.frames_due(multiplier) {
+                let instructions = chip_8_context.instructions_left_in_frame();
+                run(&mut chip_8_context, &mut paused, instructions)?;
+                if paused {
+                    break;
+                }
+            }
+        } else {
+            // Uncapped: as many frames as fit in the time of one real frame.
+            let deadline = Instant::now() + FRAME_DURATION;
+            while !paused && Instant::now() < deadline {
+                let instructions = chip_8_context.instructions_left_in_frame();
+                run(&mut chip_8_context, &mut paused, instructions)?;
+            }
+            pacer.reset();
+        }
+        chip_8_context.present()?;
+        if let Some(viewer) = &mut memory_viewer {
+            viewer.draw(&chip_8_context)?;
+        }
+
+        let new_title = match (SPEEDS[speed], paused) {
+            (Some(multiplier), false) => format!("CHIP-8 ({multiplier}x)"),
+            (Some(multiplier), true) => format!("CHIP-8 ({multiplier}x, paused)"),
+            (None, false) => "CHIP-8 (uncapped)".to_string(),
+            (None, true) => "CHIP-8 (uncapped, paused)".to_string(),
+        };
+        if new_title != title {
+            chip_8_context.set_window_title(&new_title)?;
+            title = new_title;
+        }
+        // With vsync, presenting already waited for the display.
+        if !options.vsync {
+            match SPEEDS[speed] {
+                _ if paused => sleep(FRAME_DURATION),
+                Some(multiplier) => sleep(pacer.time_to_next_frame(multiplier)),
+                None => (),
+            }
+        }
+    }
+
+    finish(&mut chip_8_context, options)
+}
+
+/// Runs up to `instructions` instructions, stopping early at watchpoints and breakpoints.
+fn run_instructions(
+    chip_8_context: &mut Chip8Context,
+    instructions: u32,
+    debugger: Option<&Debugger>,
+    gdb_stub: Option<&mut GdbStub>,
+    paused: &mut bool,
+) -> Result<(), Box<dyn Error>> {
+    let was_paused = *paused;
+    let mut gdb_stub = gdb_stub;
+    for _ in 0..instructions {
+        if let Err(error) = chip_8_context.step() {
+            return match gdb_stub {
+                Some(gdb_stub) => gdb_stub.stop_on_error(error, paused),
+                None => Err(error),
+            };
+        }
+        let hit =
+            debugger.is_some_and(|debugger| debugger.check_watchpoints(chip_8_context, paused));
+        if let Some(gdb_stub) = &mut gdb_stub {
+            gdb_stub.check_stop(chip_8_context, paused)?;
+        }
+        if hit || *paused != was_paused {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn print_cheat_menu(chip_8_context: &Chip8Context) {
+    if chip_8_context.cheats().is_empty() {
+        println!("No cheats loaded, press F3 to close");
+        return;
+    }
+    println!("Cheats (press 1-9 and 0 to toggle, F3 to close):");
+    for (index, cheat) in chip_8_context.cheats().iter().enumerate().take(10) {
+        let mark = if cheat.enabled { 'x' } else { ' ' };
+        println!(
+            "  {} [{mark}] {} ({})",
+            (index + 1) % 10,
+            cheat.name,
+            cheat.effect
+        );
+    }
+}